@@ -1,3 +1,18 @@
+use std::env;
+
 fn main() {
     lalrpop::process_root().unwrap();
+    // gxhash only builds with AES instructions, see Cargo.toml and src/hash.rs
+    println!("cargo::rustc-check-cfg=cfg(gxhash)");
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let features = env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+    let has = |f: &str| features.split(',').any(|x| x == f);
+    let simd = match arch.as_str() {
+        "x86" | "x86_64" => has("sse2"),
+        "arm" | "aarch64" => has("neon"),
+        _ => false,
+    };
+    if simd && has("aes") {
+        println!("cargo::rustc-cfg=gxhash");
+    }
 }
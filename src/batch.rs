@@ -0,0 +1,284 @@
+use crate::{
+    Args, InputError, Report, check_explicit, check_input,
+    output::{Format, Status, print_header, print_record, value_name},
+    read_input,
+};
+use rust_gkat::syntax::*;
+use rust_gkat::Solver;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// Result of one file in a batch.
+struct Entry {
+    path: PathBuf,
+    time: Duration,
+    report: Result<Report, InputError>,
+}
+
+// Check every file matched by paths on a pool of workers, each owning its
+// own gkat and solver. Returns the most severe failure, if any.
+pub fn run(paths: &[String], jobs: Option<usize>, args: &Args) -> Result<Status, String> {
+    let files = expand(paths)?;
+    let jobs = jobs
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .clamp(1, files.len());
+    let start = Instant::now();
+    print_header(args);
+    let next = AtomicUsize::new(0);
+    let entries = Mutex::new(vec![]);
+    thread::scope(|s| {
+        for _ in 0..jobs {
+            s.spawn(|| match args.solver.backend() {
+                #[cfg(feature = "cudd")]
+                Solver::BDD => worker(BDDGkat::new, &files, &next, &entries, args),
+                #[cfg(feature = "sat")]
+                Solver::SAT => worker(SATGkat::new, &files, &next, &entries, args),
+                #[cfg(feature = "sat")]
+                Solver::AIG => worker(AIGGkat::new, &files, &next, &entries, args),
+                #[cfg(feature = "sat")]
+                Solver::Hybrid => worker(HybridGkat::new, &files, &next, &entries, args),
+                Solver::PureBDD => worker(PureBDDGkat::new, &files, &next, &entries, args),
+                // every file goes to the explicit backend, or fails
+                Solver::Explicit => worker(PureBDDGkat::new, &files, &next, &entries, args),
+                Solver::Auto => unreachable!(),
+            });
+        }
+    });
+    let mut entries = entries.into_inner().unwrap();
+    entries.sort_by(|e1, e2| e1.path.cmp(&e2.path));
+    if args.format == Format::Text {
+        summary(&entries, args);
+        println!("{:<15}= {}", "peak memory", peak_memory());
+        println!("{:<15}= {:.2}s", "wall time", start.elapsed().as_secs_f64());
+    }
+    // expected non-equivalences are not failures here
+    let status = entries.iter().map(|e| Status::of(&e.report)).max();
+    Ok(status
+        .filter(|s| *s >= Status::Unknown)
+        .unwrap_or(Status::Equivalent))
+}
+
+fn worker<B: BExp, G: Gkat<B>>(
+    new: fn() -> G,
+    files: &[PathBuf],
+    next: &AtomicUsize,
+    entries: &Mutex<Vec<Entry>>,
+    args: &Args,
+) {
+    let mut gkat = new();
+    loop {
+        let i = next.fetch_add(1, Ordering::Relaxed);
+        let Some(path) = files.get(i) else {
+            return;
+        };
+        let start = Instant::now();
+        let report = read_input(&path.to_string_lossy(), args).and_then(|input| {
+            // explicit tables are sized for the file, and constraints and
+            // variable orders stay in the gkat, so give those a fresh one
+            Ok(if let Some(n) = args.explicit_size(&input)? {
+                check_explicit(n, input, args)
+            } else if input.constraints.is_empty() && args.var_order.is_none() {
+                check_input(&mut gkat, input, args)
+            } else {
+                check_input(&mut new(), input, args)
+            })
+        });
+        let entry = Entry {
+            path: path.clone(),
+            time: start.elapsed(),
+            report,
+        };
+        match args.format {
+            Format::Text => print_entry(&entry, args),
+            _ => print_record(&path.to_string_lossy(), entry.time, &entry.report, args),
+        }
+        entries.lock().unwrap().push(entry);
+    }
+}
+
+fn print_entry(entry: &Entry, args: &Args) {
+    let path = entry.path.display();
+    let time = entry.time.as_secs_f64();
+    // one write per entry keeps lines of concurrent workers apart
+    let mut out = match &entry.report {
+        Ok(r) => {
+            let result = match r.unknown {
+                Some(limit) => format!("unknown ({})", limit),
+                None => r.result.to_string(),
+            };
+            let status = match Status::of(&entry.report) {
+                Status::Mismatch => "  MISMATCH",
+                Status::Unknown => "  UNKNOWN",
+                _ => "",
+            };
+            let mut out = format!(
+                "{} {:.3}s {}_expected={} {}_result={}{}",
+                path, time, r.name, r.expected, r.name, result, status
+            );
+            if r.solver != value_name(args.solver) {
+                out += &format!("\n  {:<13}= {}", "solver", r.solver);
+            }
+            for (key, value) in &r.fields {
+                out += &format!("\n  {:<13}= {}", key, value);
+            }
+            if args.stats {
+                for (key, n) in r.stats.counters() {
+                    out += &format!("\n  {:<15}= {}", key, n);
+                }
+            }
+            out
+        }
+        Err(e) => format!("{} {:.3}s  ERROR {}", path, time, e),
+    };
+    out.push('\n');
+    print!("{}", out);
+}
+
+// Totals per directory, laid out like the tables in the README.
+fn summary(entries: &[Entry], args: &Args) {
+    // files, mismatches, unknowns and errors per directory
+    let mut rows: Vec<(String, [usize; 4], f64)> = vec![];
+    for e in entries {
+        let dir = match e.path.parent().and_then(|p| p.file_name()) {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => ".".to_string(),
+        };
+        if rows.last().is_none_or(|r| r.0 != dir) {
+            rows.push((dir, [0; 4], 0.0));
+        }
+        let row = rows.last_mut().unwrap();
+        let status = Status::of(&e.report);
+        row.1[0] += 1;
+        row.1[1] += (status == Status::Mismatch) as usize;
+        row.1[2] += (status == Status::Unknown) as usize;
+        row.1[3] += (status == Status::InputError) as usize;
+        row.2 += e.time.as_secs_f64();
+    }
+    let kernel = value_name(args.kernel);
+    let time = format!("Time ({}-{})", kernel, value_name(args.solver));
+    let width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0).max(9);
+    println!();
+    println!(
+        "| {:<width$} | Files | Mismatches | Unknown | Errors | {} |",
+        "Benchmark", time
+    );
+    println!(
+        "| {} | ----- | ---------- | ------- | ------ | {} |",
+        "-".repeat(width),
+        "-".repeat(time.len())
+    );
+    for (dir, [files, mismatches, unknowns, errors], secs) in rows {
+        println!(
+            "| {:<width$} | {:<5} | {:<10} | {:<7} | {:<6} | {:<tw$.2} |",
+            dir,
+            files,
+            mismatches,
+            unknowns,
+            errors,
+            secs,
+            tw = time.len()
+        );
+    }
+    println!();
+}
+
+fn peak_memory() -> String {
+    // VmHWM is the resident set high-water mark of the whole process
+    let status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+    for line in status.lines() {
+        if let Some(kb) = line.strip_prefix("VmHWM:")
+            && let Ok(kb) = kb.trim().trim_end_matches("kB").trim().parse::<f64>()
+        {
+            return format!("{:.1} MB", kb / 1024.0);
+        }
+    }
+    "n/a".to_string()
+}
+
+// Files under the given directories, files or glob patterns, sorted.
+fn expand(paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![];
+    for p in paths {
+        let before = files.len();
+        if p.contains(['*', '?']) {
+            for path in glob(p) {
+                walk(&path, &mut files);
+            }
+        } else {
+            walk(Path::new(p), &mut files);
+        }
+        if files.len() == before {
+            return Err(format!("no input files match {}", p));
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn walk(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        let Ok(dir) = fs::read_dir(path) else {
+            return;
+        };
+        for entry in dir.flatten() {
+            walk(&entry.path(), files);
+        }
+    } else if path.is_file() {
+        files.push(path.to_path_buf());
+    }
+}
+
+// Expand `*` and `?` wildcards one path component at a time.
+fn glob(pattern: &str) -> Vec<PathBuf> {
+    let mut paths = vec![if pattern.starts_with('/') {
+        PathBuf::from("/")
+    } else {
+        PathBuf::new()
+    }];
+    for part in pattern.split('/').filter(|s| !s.is_empty()) {
+        let mut next = vec![];
+        for path in paths {
+            if !part.contains(['*', '?']) {
+                next.push(path.join(part));
+                continue;
+            }
+            let dir = if path.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                path.as_path()
+            };
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !name.starts_with('.') && wildcard(part.as_bytes(), name.as_bytes()) {
+                    next.push(path.join(name));
+                }
+            }
+        }
+        paths = next;
+    }
+    paths.retain(|p| p.exists());
+    paths
+}
+
+fn wildcard(pat: &[u8], s: &[u8]) -> bool {
+    match (pat.first(), s.first()) {
+        (None, _) => s.is_empty(),
+        (Some(b'*'), _) => wildcard(&pat[1..], s) || (!s.is_empty() && wildcard(pat, &s[1..])),
+        (Some(b'?'), Some(_)) => wildcard(&pat[1..], &s[1..]),
+        (Some(c), Some(d)) if c == d => wildcard(&pat[1..], &s[1..]),
+        _ => false,
+    }
+}
@@ -0,0 +1,353 @@
+use super::*;
+use crate::hash::{HashMap, HashMapExt, HashSet, HashSetExt};
+
+// Independent checker for bisimulation certificates. It deliberately shares
+// no code with the kernels: derivatives, dead states and the equivalence
+// closure are all recomputed here.
+pub struct Checker<B> {
+    eps: HashMap<u64, B>,
+    delta: HashMap<u64, Vec<(B, u64, u64)>>,
+    dead: HashSet<u64>,
+    parent: HashMap<u64, u64>,
+    drv_cache: HashMap<Exp<B>, Vec<Deriv<B>>>,
+}
+
+// Guard, target and action of one derivative of an expression.
+type Deriv<B> = (B, Exp<B>, u64);
+
+impl<B: BExp> Default for Checker<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: BExp> Checker<B> {
+    pub fn new() -> Self {
+        Self {
+            eps: HashMap::new(),
+            delta: HashMap::new(),
+            dead: HashSet::new(),
+            parent: HashMap::new(),
+            drv_cache: HashMap::new(),
+        }
+    }
+
+    pub fn check<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        cert: parsing::Certificate,
+    ) -> Result<(), String> {
+        for st in cert.states {
+            let eps = gkat.from_bexp(st.eps);
+            let trans = st.trans.into_iter().map(|(b, a, next)| {
+                let b = gkat.from_bexp(b);
+                (b, next, act_id(gkat, a))
+            });
+            let trans = trans.collect();
+            if self.eps.insert(st.id, eps).is_some() {
+                return Err(format!("state {} is declared twice", st.id));
+            }
+            self.delta.insert(st.id, trans);
+        }
+        for trans in self.delta.values() {
+            if let Some((_, next, _)) = trans.iter().find(|t| !self.eps.contains_key(&t.1)) {
+                return Err(format!("transition to undeclared state {}", next));
+            }
+        }
+        let (i, j) = cert.start;
+        for st in cert.pairs.iter().flat_map(|(i, j)| [i, j]).chain([&i, &j]) {
+            if !self.eps.contains_key(st) {
+                return Err(format!("relation mentions undeclared state {}", st));
+            }
+        }
+        self.check_deterministic(gkat)?;
+        self.check_exps(gkat, cert.exps)?;
+        self.compute_dead(gkat);
+        for (i, j) in &cert.pairs {
+            self.union(*i, *j);
+        }
+        if !self.related_or_dead(i, j) {
+            return Err(format!("start states {} and {} are not related", i, j));
+        }
+        for (i, j) in &cert.pairs {
+            self.check_pair(gkat, *i, *j)?;
+        }
+        Ok(())
+    }
+
+    // Guards leaving a state must be disjoint from each other and from eps.
+    fn check_deterministic<G: Gkat<B>>(&self, gkat: &mut G) -> Result<(), String> {
+        for (st, trans) in &self.delta {
+            let mut seen = self.eps.get(st).unwrap().clone();
+            for (b, _, _) in trans {
+                let overlap = gkat.mk_and(&seen, b);
+                if !gkat.is_false(&overlap) {
+                    return Err(format!("state {} has overlapping guards", st));
+                }
+                seen = gkat.mk_or(&seen, b);
+            }
+        }
+        Ok(())
+    }
+
+    // States labelled by expressions must carry their exact derivatives.
+    fn check_exps<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exps: Vec<(u64, parsing::Exp)>,
+    ) -> Result<(), String> {
+        let mut exp_of = HashMap::new();
+        for (id, e) in exps {
+            if !self.eps.contains_key(&id) {
+                return Err(format!("expression for undeclared state {}", id));
+            }
+            exp_of.insert(id, gkat.from_exp(e));
+        }
+        for (id, exp) in &exp_of {
+            let eps = epsilon(gkat, exp);
+            if !gkat.is_equiv(&eps, self.eps.get(id).unwrap()) {
+                return Err(format!("state {} has a wrong epsilon", id));
+            }
+            // compare guards per (successor, action), independent of order
+            let mut groups: HashMap<(Exp<B>, u64), (B, B)> = HashMap::new();
+            for (b, e, a) in self.derivative(gkat, exp) {
+                let zero = gkat.mk_zero();
+                let entry = groups.entry((e, a)).or_insert((zero.clone(), zero));
+                entry.0 = gkat.mk_or(&entry.0, &b);
+            }
+            for (b, next, a) in self.delta.get(id).unwrap() {
+                let e = match exp_of.get(next) {
+                    Some(e) => e.clone(),
+                    None => return Err(format!("state {} has no expression", next)),
+                };
+                let zero = gkat.mk_zero();
+                let entry = groups.entry((e, *a)).or_insert((zero.clone(), zero));
+                entry.1 = gkat.mk_or(&entry.1, b);
+            }
+            for (computed, listed) in groups.values() {
+                if !gkat.is_equiv(computed, listed) {
+                    return Err(format!("state {} has wrong transitions", id));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn derivative<G: Gkat<B>>(&mut self, gkat: &mut G, exp: &Exp<B>) -> Vec<(B, Exp<B>, u64)> {
+        if let Some(d) = self.drv_cache.get(exp) {
+            return d.clone();
+        }
+        use Exp_::*;
+        let d = match exp.get() {
+            Act(a) => {
+                let one = gkat.mk_one();
+                vec![(one.clone(), gkat.mk_test(one), *a)]
+            }
+            Test(_) => vec![],
+            Seq(p1, p2) => {
+                let eps1 = epsilon(gkat, p1);
+                let mut d = vec![];
+                for (b, e, a) in self.derivative(gkat, p1) {
+                    d.push((b, gkat.mk_seq(e, p2.clone()), a));
+                }
+                for (b, e, a) in self.derivative(gkat, p2) {
+                    d.push((gkat.mk_and(&eps1, &b), e, a));
+                }
+                d
+            }
+            Ifte(c, p1, p2) => {
+                let nc = gkat.mk_not(c);
+                let mut d = vec![];
+                for (b, e, a) in self.derivative(gkat, p1) {
+                    d.push((gkat.mk_and(c, &b), e, a));
+                }
+                for (b, e, a) in self.derivative(gkat, p2) {
+                    d.push((gkat.mk_and(&nc, &b), e, a));
+                }
+                d
+            }
+            While(c, p) => {
+                let mut d = vec![];
+                for (b, e, a) in self.derivative(gkat, p) {
+                    d.push((gkat.mk_and(c, &b), gkat.mk_seq(e, exp.clone()), a));
+                }
+                d
+            }
+        };
+        let d: Vec<_> = d
+            .into_iter()
+            .filter(|(b, _, _)| !gkat.is_false(b))
+            .collect();
+        self.drv_cache.insert(exp.clone(), d.clone());
+        d
+    }
+
+    // A state is live if it can reach a state with a satisfiable eps.
+    fn compute_dead<G: Gkat<B>>(&mut self, gkat: &mut G) {
+        let mut preds: HashMap<u64, Vec<u64>> = HashMap::new();
+        for (st, trans) in &self.delta {
+            for (b, next, _) in trans {
+                if !gkat.is_false(b) {
+                    preds.entry(*next).or_default().push(*st);
+                }
+            }
+        }
+        let mut live = HashSet::new();
+        let mut stack: Vec<_> = self
+            .eps
+            .iter()
+            .filter(|(_, b)| !gkat.is_false(b))
+            .map(|(st, _)| *st)
+            .collect();
+        while let Some(st) = stack.pop() {
+            if live.insert(st) {
+                stack.extend(preds.get(&st).into_iter().flatten());
+            }
+        }
+        self.dead = self
+            .eps
+            .keys()
+            .filter(|st| !live.contains(*st))
+            .cloned()
+            .collect();
+    }
+
+    fn find(&mut self, st: u64) -> u64 {
+        let mut root = st;
+        while let Some(p) = self.parent.get(&root) {
+            root = *p;
+        }
+        let mut cur = st;
+        while cur != root {
+            let next = self.parent.insert(cur, root).unwrap();
+            cur = next;
+        }
+        root
+    }
+
+    fn union(&mut self, i: u64, j: u64) {
+        let (ri, rj) = (self.find(i), self.find(j));
+        if ri != rj {
+            self.parent.insert(ri, rj);
+        }
+    }
+
+    fn related_or_dead(&mut self, i: u64, j: u64) -> bool {
+        self.find(i) == self.find(j) || (self.dead.contains(&i) && self.dead.contains(&j))
+    }
+
+    fn check_pair<G: Gkat<B>>(&mut self, gkat: &mut G, i: u64, j: u64) -> Result<(), String> {
+        let eps1 = self.eps.get(&i).unwrap().clone();
+        let eps2 = self.eps.get(&j).unwrap().clone();
+        if !gkat.is_equiv(&eps1, &eps2) {
+            return Err(format!("pair ({}, {}) disagrees on acceptance", i, j));
+        }
+        let delta1 = self.delta.get(&i).unwrap().clone();
+        let delta2 = self.delta.get(&j).unwrap().clone();
+        for (st, own, other, other_eps) in
+            [(i, &delta1, &delta2, &eps2), (j, &delta2, &delta1, &eps1)]
+        {
+            let mut reject = gkat.mk_not(other_eps);
+            for (b, _, _) in other.iter() {
+                let nb = gkat.mk_not(b);
+                reject = gkat.mk_and(&reject, &nb);
+            }
+            for (b, next, _) in own.iter() {
+                let b = gkat.mk_and(b, &reject);
+                if !gkat.is_false(&b) && !self.dead.contains(next) {
+                    return Err(format!("state {} has an unmatched live transition", st));
+                }
+            }
+        }
+        for (b1, next1, a1) in &delta1 {
+            for (b2, next2, a2) in &delta2 {
+                let b = gkat.mk_and(b1, b2);
+                if gkat.is_false(&b) {
+                    continue;
+                }
+                let ok = if a1 == a2 {
+                    self.related_or_dead(*next1, *next2)
+                } else {
+                    self.dead.contains(next1) && self.dead.contains(next2)
+                };
+                if !ok {
+                    return Err(format!("pair ({}, {}) is not closed", i, j));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn act_id<B: BExp, G: Gkat<B>>(gkat: &mut G, name: String) -> u64 {
+    match gkat.mk_act(name).get() {
+        Exp_::Act(a) => *a,
+        _ => unreachable!(),
+    }
+}
+
+fn epsilon<B: BExp, G: Gkat<B>>(gkat: &mut G, exp: &Exp<B>) -> B {
+    use Exp_::*;
+    match exp.get() {
+        Act(_) => gkat.mk_zero(),
+        Test(b) => b.clone(),
+        Seq(p1, p2) => {
+            let b1 = epsilon(gkat, p1);
+            let b2 = epsilon(gkat, p2);
+            gkat.mk_and(&b1, &b2)
+        }
+        Ifte(c, p1, p2) => {
+            let b1 = epsilon(gkat, p1);
+            let b2 = epsilon(gkat, p2);
+            let cb1 = gkat.mk_and(c, &b1);
+            let nc = gkat.mk_not(c);
+            let ncb2 = gkat.mk_and(&nc, &b2);
+            gkat.mk_or(&cb1, &ncb2)
+        }
+        While(c, _) => gkat.mk_not(c),
+    }
+}
+
+#[test]
+pub fn test() {
+    use crate::kernel1;
+    use crate::kernel2;
+    use crate::parsing::parse_certificate;
+    use crate::parsing::spec::ExpParser;
+    let cases = [
+        ("(if b1 p1 p2)", "(if (not b1) p2 p1)"),
+        ("(seq p1 (seq p2 p3))", "(seq (seq p1 p2) p3)"),
+        ("(while b1 p1)", "(if b1 (seq p1 (while b1 p1)) (test 1))"),
+        ("(while b1 (seq p1 (test 0)))", "(test (not b1))"),
+    ];
+    for (e1, e2) in cases {
+        let mut gkat = PureBDDGkat::new();
+        let exp1 = gkat.from_exp(ExpParser::new().parse(e1).unwrap());
+        let exp2 = gkat.from_exp(ExpParser::new().parse(e2).unwrap());
+        let mut solver = kernel1::Solver::new();
+        solver.record_relation();
+        assert_eq!(solver.equiv_iter(&mut gkat, &exp1, &exp2), Ok(true));
+        let cert = solver.certificate(&mut gkat, &exp1, &exp2).unwrap();
+        let cert = parse_certificate(cert.to_string()).unwrap();
+        assert_eq!(Checker::new().check(&mut PureBDDGkat::new(), cert), Ok(()));
+
+        let mut solver = kernel2::Solver::new();
+        solver.record_relation();
+        let (i, m) = solver.mk_automaton(&mut gkat, &exp1);
+        let (j, n) = solver.mk_automaton(&mut gkat, &exp2);
+        assert_eq!(solver.equiv_iter(&mut gkat, i, j, &m, &n), Ok(true));
+        let mut cert = solver.certificate(&mut gkat, i, j, &m, &n).unwrap();
+        cert = parse_certificate(cert.to_string()).unwrap();
+        assert_eq!(
+            Checker::new().check(&mut PureBDDGkat::new(), cert.clone()),
+            Ok(())
+        );
+        // forgetting the relation must be caught
+        cert.pairs.clear();
+        assert!(Checker::new().check(&mut PureBDDGkat::new(), cert).is_err());
+        // so must a relation that was never recorded
+        let mut solver = kernel2::Solver::new();
+        assert_eq!(solver.equiv_iter(&mut gkat, i, j, &m, &n), Ok(true));
+        assert!(solver.certificate(&mut gkat, i, j, &m, &n).is_none());
+    }
+}
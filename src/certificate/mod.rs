@@ -0,0 +1,5 @@
+mod check;
+
+use crate::parsing;
+use crate::syntax::*;
+pub use check::*;
@@ -0,0 +1,105 @@
+// Hash maps and sets used throughout the crate. gxhash needs AES
+// instructions, so build.rs sets cfg(gxhash) only on targets that have
+// them; elsewhere a portable multiply-rotate hasher takes its place.
+
+#[cfg(gxhash)]
+pub use gxhash::{GxBuildHasher as BuildHasher, HashMap, HashMapExt, HashSet, HashSetExt};
+
+#[cfg(not(gxhash))]
+pub use portable::*;
+
+#[cfg(not(gxhash))]
+mod portable {
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    // The hasher of rustc (FxHash): one rotate, xor and multiply per word.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct FxHasher {
+        hash: u64,
+    }
+
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    impl FxHasher {
+        #[inline]
+        fn add(&mut self, word: u64) {
+            self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+        }
+    }
+
+    impl Hasher for FxHasher {
+        #[inline]
+        fn write(&mut self, bytes: &[u8]) {
+            for chunk in bytes.chunks(8) {
+                let mut word = [0; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                self.add(u64::from_le_bytes(word));
+            }
+        }
+
+        #[inline]
+        fn write_u8(&mut self, i: u8) {
+            self.add(i as u64);
+        }
+
+        #[inline]
+        fn write_u16(&mut self, i: u16) {
+            self.add(i as u64);
+        }
+
+        #[inline]
+        fn write_u32(&mut self, i: u32) {
+            self.add(i as u64);
+        }
+
+        #[inline]
+        fn write_u64(&mut self, i: u64) {
+            self.add(i);
+        }
+
+        #[inline]
+        fn write_usize(&mut self, i: usize) {
+            self.add(i as u64);
+        }
+
+        #[inline]
+        fn finish(&self) -> u64 {
+            self.hash
+        }
+    }
+
+    pub type BuildHasher = BuildHasherDefault<FxHasher>;
+    pub type HashMap<K, V> = std::collections::HashMap<K, V, BuildHasher>;
+    pub type HashSet<T> = std::collections::HashSet<T, BuildHasher>;
+
+    // Constructors that the std types only offer for RandomState.
+    pub trait HashMapExt {
+        fn new() -> Self;
+        fn with_capacity(capacity: usize) -> Self;
+    }
+
+    pub trait HashSetExt {
+        fn new() -> Self;
+        fn with_capacity(capacity: usize) -> Self;
+    }
+
+    impl<K, V> HashMapExt for HashMap<K, V> {
+        fn new() -> Self {
+            HashMap::with_hasher(BuildHasher::default())
+        }
+
+        fn with_capacity(capacity: usize) -> Self {
+            HashMap::with_capacity_and_hasher(capacity, BuildHasher::default())
+        }
+    }
+
+    impl<T> HashSetExt for HashSet<T> {
+        fn new() -> Self {
+            HashSet::with_hasher(BuildHasher::default())
+        }
+
+        fn with_capacity(capacity: usize) -> Self {
+            HashSet::with_capacity_and_hasher(capacity, BuildHasher::default())
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use super::*;
+use crate::hash::{HashMap, HashMapExt};
+use crate::parsing::{CertState, Certificate};
+
+impl<B: BExp> Solver<B> {
+    // Export the derivative automaton and the relation built by equiv_iter,
+    // or None unless it ran after record_relation.
+    pub fn certificate<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp1: &Exp<B>,
+        exp2: &Exp<B>,
+    ) -> Option<Certificate> {
+        let related = self.relation()?.to_vec();
+        let mut ids: HashMap<Exp<B>, u64> = HashMap::new();
+        let mut queue = vec![];
+        for exp in [exp1, exp2] {
+            if !ids.contains_key(exp) {
+                ids.insert(exp.clone(), ids.len() as u64);
+                queue.push(exp.clone());
+            }
+        }
+        let mut states = vec![];
+        let mut exps = vec![];
+        let mut next = 0;
+        while next < queue.len() {
+            let exp = queue[next].clone();
+            next += 1;
+            let eps = self.epsilon(gkat, &exp);
+            let mut trans = vec![];
+            for (b, e, a) in self.derivative(gkat, &exp) {
+                let st = match ids.get(&e) {
+                    Some(st) => *st,
+                    None => {
+                        let st = ids.len() as u64;
+                        ids.insert(e.clone(), st);
+                        queue.push(e);
+                        st
+                    }
+                };
+                trans.push((gkat.to_bexp(&b), gkat.act_name(a), st));
+            }
+            let id = ids[&exp];
+            exps.push((id, gkat.to_exp(&exp)));
+            states.push(CertState {
+                id,
+                eps: gkat.to_bexp(&eps),
+                trans,
+            });
+        }
+        let pairs = related.iter().map(|(e1, e2)| (ids[e1], ids[e2]));
+        Some(Certificate {
+            start: (ids[exp1], ids[exp2]),
+            states,
+            exps,
+            pairs: pairs.collect(),
+        })
+    }
+}
@@ -26,7 +26,7 @@ impl<B: BExp> Solver<B> {
             While(b, _) => gkat.mk_not(b),
         };
         self.set_eps(m.clone(), eps.clone());
-        return eps;
+        eps
     }
 
     #[recursive]
@@ -37,11 +37,19 @@ impl<B: BExp> Solver<B> {
         use Exp_::*;
         let deriv = match exp.get() {
             Test(_) => vec![],
-            Act(n) => {
-                let one_exp = gkat.mk_one();
-                let e = gkat.mk_test(one_exp.clone());
-                vec![(one_exp, e, *n)]
-            }
+            Act(n) => match self.get_post(*n) {
+                // continue with the postcondition holding
+                Some(post) => post
+                    .clone()
+                    .into_iter()
+                    .map(|(b, c)| (b, gkat.mk_test(c), *n))
+                    .collect(),
+                None => {
+                    let one_exp = gkat.mk_one();
+                    let e = gkat.mk_test(one_exp.clone());
+                    vec![(one_exp, e, *n)]
+                }
+            },
             Ifte(b, p1, p2) => {
                 let nb = gkat.mk_not(b);
                 let dexp1 = self.derivative(gkat, p1);
@@ -1,43 +1,94 @@
 use super::*;
 
 impl<B: BExp> Solver<B> {
-    pub fn equiv_iter<G: Gkat<B>>(&mut self, gkat: &mut G, exp1: &Exp<B>, exp2: &Exp<B>) -> bool {
-        let mut stack = vec![(exp1.clone(), exp2.clone())];
-        while let Some((exp1, exp2)) = stack.pop() {
+    pub fn equiv_iter<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp1: &Exp<B>,
+        exp2: &Exp<B>,
+    ) -> Result<bool, Limit> {
+        let mismatch = self.search(gkat, exp1, exp2, false);
+        self.verdict(gkat, mismatch.is_none())
+    }
+
+    pub fn equiv_search<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp1: &Exp<B>,
+        exp2: &Exp<B>,
+    ) -> Result<Option<Mismatch<B, Exp<B>>>, Limit> {
+        let mismatch = self.search(gkat, exp1, exp2, true);
+        self.verdict(gkat, mismatch)
+    }
+
+    // Find a mismatch; its path is only recorded when traced, which keeps
+    // plain equivalence checks free of guard clones.
+    fn search<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp1: &Exp<B>,
+        exp2: &Exp<B>,
+        traced: bool,
+    ) -> Option<Mismatch<B, Exp<B>>> {
+        // parent pointers for reconstructing failing paths
+        let mut trace: Vec<(Option<usize>, B, u64)> = vec![];
+        let mut stack = vec![(exp1.clone(), exp2.clone(), None)];
+        while let Some((exp1, exp2, parent)) = stack.pop() {
             let mut exp1_uf = self.get_uf(&exp1);
             let mut exp2_uf = self.get_uf(&exp2);
 
-            if exp1_uf.equiv(&exp2_uf) {
-                continue;
-            } else if self.known_dead(&exp1) && self.is_dead(gkat, &exp2) {
-                continue;
-            } else if self.known_dead(&exp2) && self.is_dead(gkat, &exp1) {
+            if exp1_uf.equiv(&exp2_uf)
+                || (self.known_dead(&exp1) && self.dead(gkat, &exp2))
+                || (self.known_dead(&exp2) && self.dead(gkat, &exp1))
+            {
                 continue;
             } else {
+                self.stats.pairs += 1;
+                if self.out_of_budget(gkat) {
+                    return None;
+                }
                 let eps1 = self.epsilon(gkat, &exp1);
                 let eps2 = self.epsilon(gkat, &exp2);
                 let dexp1 = self.derivative(gkat, &exp1);
                 let dexp2 = self.derivative(gkat, &exp2);
 
                 if !(gkat.is_equiv(&eps1, &eps2)) {
-                    return false;
+                    let neps2 = gkat.mk_not(&eps2);
+                    let guard = gkat.mk_and(&eps1, &neps2);
+                    let left = !gkat.is_false(&guard);
+                    let guard = if left {
+                        guard
+                    } else {
+                        let neps1 = gkat.mk_not(&eps1);
+                        gkat.mk_and(&neps1, &eps2)
+                    };
+                    return Some(Mismatch::new(&trace, parent, guard, None, left));
                 }
                 let reject1 = self.reject(gkat, &eps1, &dexp1);
-                let assert1 = dexp2.iter().all(|(b0, exp, _)| {
+                let assert1 = dexp2.iter().find_map(|(b0, exp, a)| {
                     let b1 = gkat.mk_and(&reject1, b0);
-                    gkat.is_false(&b1) || self.is_dead(gkat, &exp)
+                    if gkat.is_false(&b1) || self.dead(gkat, exp) {
+                        None
+                    } else {
+                        Some((b1, exp.clone(), *a))
+                    }
                 });
-                if !assert1 {
-                    return false;
+                if let Some((guard, exp, a)) = assert1 {
+                    return Some(Mismatch::new(&trace, parent, guard, Some((a, exp)), false));
                 }
                 let reject2 = self.reject(gkat, &eps2, &dexp2);
-                let assert2 = dexp1.iter().all(|(b0, exp, _)| {
+                let assert2 = dexp1.iter().find_map(|(b0, exp, a)| {
                     let b1 = gkat.mk_and(&reject2, b0);
-                    gkat.is_false(&b1) || self.is_dead(gkat, &exp)
+                    if gkat.is_false(&b1) || self.dead(gkat, exp) {
+                        None
+                    } else {
+                        Some((b1, exp.clone(), *a))
+                    }
                 });
-                if !assert2 {
-                    return false;
+                if let Some((guard, exp, a)) = assert2 {
+                    return Some(Mismatch::new(&trace, parent, guard, Some((a, exp)), true));
                 }
+                self.relate(&exp1, &exp2);
                 for (be1, next_exp1, p) in &dexp1 {
                     for (be2, next_exp2, q) in &dexp2 {
                         let b1b2 = gkat.mk_and(be1, be2);
@@ -45,18 +96,29 @@ impl<B: BExp> Solver<B> {
                             continue;
                         } else if *p == *q {
                             exp1_uf.union(&mut exp2_uf);
-                            stack.push((next_exp1.clone(), next_exp2.clone()));
+                            self.stats.unions += 1;
+                            let next = if traced {
+                                trace.push((parent, b1b2, *p));
+                                Some(trace.len() - 1)
+                            } else {
+                                None
+                            };
+                            stack.push((next_exp1.clone(), next_exp2.clone(), next));
                         } else {
-                            let result1 = self.is_dead(gkat, &next_exp1);
-                            let result2 = self.is_dead(gkat, &next_exp2);
-                            if !(result1 && result2) {
-                                return false;
+                            let result1 = self.dead(gkat, next_exp1);
+                            let result2 = self.dead(gkat, next_exp2);
+                            if !result1 {
+                                let suffix = Some((*p, next_exp1.clone()));
+                                return Some(Mismatch::new(&trace, parent, b1b2, suffix, true));
+                            } else if !result2 {
+                                let suffix = Some((*q, next_exp2.clone()));
+                                return Some(Mismatch::new(&trace, parent, b1b2, suffix, false));
                             }
                         }
                     }
                 }
             }
         }
-        return true;
+        None
     }
 }
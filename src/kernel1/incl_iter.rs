@@ -0,0 +1,129 @@
+use super::*;
+use crate::hash::{HashSet, HashSetExt};
+
+impl<B: BExp> Solver<B> {
+    pub fn incl_iter<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp1: &Exp<B>,
+        exp2: &Exp<B>,
+    ) -> Result<bool, Limit> {
+        Ok(self.incl_search(gkat, exp1, exp2)?.is_none())
+    }
+
+    pub fn incl_search<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp1: &Exp<B>,
+        exp2: &Exp<B>,
+    ) -> Result<Option<Mismatch<B, Exp<B>>>, Limit> {
+        let mismatch = self.incl_mismatch(gkat, exp1, exp2);
+        self.verdict(gkat, mismatch)
+    }
+
+    // Check that the language of exp1 is contained in the language of exp2.
+    // Inclusion is not symmetric, so visited pairs replace union-find.
+    fn incl_mismatch<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp1: &Exp<B>,
+        exp2: &Exp<B>,
+    ) -> Option<Mismatch<B, Exp<B>>> {
+        // parent pointers for reconstructing failing paths
+        let mut trace: Vec<(Option<usize>, B, u64)> = vec![];
+        let mut visited = HashSet::new();
+        let mut stack = vec![(exp1.clone(), exp2.clone(), None)];
+        while let Some((exp1, exp2, parent)) = stack.pop() {
+            let seen = visited.contains(&(exp1.clone(), exp2.clone()));
+            if exp1 == exp2 || seen || self.dead(gkat, &exp1) {
+                continue;
+            }
+            visited.insert((exp1.clone(), exp2.clone()));
+            self.stats.pairs += 1;
+            if self.out_of_budget(gkat) {
+                return None;
+            }
+            let eps1 = self.epsilon(gkat, &exp1);
+            let eps2 = self.epsilon(gkat, &exp2);
+            let dexp1 = self.derivative(gkat, &exp1);
+            let dexp2 = self.derivative(gkat, &exp2);
+
+            let neps2 = gkat.mk_not(&eps2);
+            let guard = gkat.mk_and(&eps1, &neps2);
+            if !gkat.is_false(&guard) {
+                return Some(Mismatch::new(&trace, parent, guard, None, true));
+            }
+            // atoms on which exp2 cannot take any step
+            let stuck2 = dexp2.iter().fold(gkat.mk_one(), |acc, (b, _, _)| {
+                let nb = gkat.mk_not(b);
+                gkat.mk_and(&acc, &nb)
+            });
+            for (be1, next_exp1, p) in &dexp1 {
+                if self.dead(gkat, next_exp1) {
+                    continue;
+                }
+                let guard = gkat.mk_and(be1, &stuck2);
+                if !gkat.is_false(&guard) {
+                    let suffix = Some((*p, next_exp1.clone()));
+                    return Some(Mismatch::new(&trace, parent, guard, suffix, true));
+                }
+                for (be2, next_exp2, q) in &dexp2 {
+                    let b1b2 = gkat.mk_and(be1, be2);
+                    if gkat.is_false(&b1b2) {
+                        continue;
+                    } else if *p == *q {
+                        trace.push((parent, b1b2, *p));
+                        stack.push((next_exp1.clone(), next_exp2.clone(), Some(trace.len() - 1)));
+                    } else {
+                        let suffix = Some((*p, next_exp1.clone()));
+                        return Some(Mismatch::new(&trace, parent, b1b2, suffix, true));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub fn incl_witness<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp1: &Exp<B>,
+        exp2: &Exp<B>,
+    ) -> Result<Option<Witness>, Limit> {
+        let Some(mismatch) = self.incl_search(gkat, exp1, exp2)? else {
+            return Ok(None);
+        };
+        // the verdict is in, so build the witness without limits
+        gkat.set_limits(&Limits::default());
+        let w = self.mk_witness(gkat, mismatch);
+        assert!(self.accepts(gkat, exp1, &w.string));
+        assert!(!self.accepts(gkat, exp2, &w.string));
+        Ok(Some(w))
+    }
+}
+
+#[test]
+pub fn test() {
+    use crate::parsing::spec::ExpParser;
+    let cases = [
+        ("(seq (test b1) p1)", "p1", true),
+        ("p1", "(seq (test b1) p1)", false),
+        ("(while b1 (seq p1 (test 0)))", "(while b1 p1)", true),
+        ("(while b1 p1)", "(while b1 (seq p1 p1))", false),
+        ("(if b1 p1 (test 0))", "(if b1 p1 p2)", true),
+        ("(if b1 p1 p2)", "(if b1 p1 p3)", false),
+    ];
+    for (e1, e2, expected) in cases {
+        let mut gkat = PureBDDGkat::new();
+        let mut solver = Solver::new();
+        let exp1 = gkat.from_exp(ExpParser::new().parse(e1).unwrap());
+        let exp2 = gkat.from_exp(ExpParser::new().parse(e2).unwrap());
+        match solver.incl_witness(&mut gkat, &exp1, &exp2).unwrap() {
+            Some(w) => {
+                assert!(!expected);
+                assert!(w.left_accepts);
+            }
+            None => assert!(expected),
+        }
+    }
+}
@@ -1,7 +1,11 @@
+mod certificate;
 mod derivative;
 mod equiv_iter;
 mod guard;
+mod incl_iter;
 mod solver;
+mod triple;
+mod witness;
 
 use crate::syntax::*;
 use guard::*;
@@ -1,6 +1,7 @@
 use super::*;
+use crate::hash::{HashMap, HashSet};
 use disjoint_sets::UnionFindNode;
-use gxhash::{HashMap, HashSet};
+use std::time::Instant;
 
 pub type Deriv<B> = Vec<(B, Exp<B>, u64)>;
 
@@ -9,11 +10,27 @@ pub struct Solver<B> {
     dead_states: HashSet<Exp<B>>,
     explored: HashSet<Exp<B>>,
     uf_table: HashMap<Exp<B>, UnionFindNode<()>>,
+    // pairs expanded by equiv_iter, recorded for certificates
+    related: Option<Vec<(Exp<B>, Exp<B>)>>,
+    // postconditions of actions
+    hyps: Hypotheses<B>,
+    // resource limits
+    deadline: Option<Instant>,
+    max_pairs: Option<usize>,
+    stopped: Option<Limit>,
+    // statistics
+    pub stats: SolverStats,
     // caching
     eps_cache: HashMap<Exp<B>, B>,
     drv_cache: HashMap<Exp<B>, Deriv<B>>,
 }
 
+impl<B: BExp> Default for Solver<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<B: BExp> Solver<B> {
     pub fn new() -> Self {
         Solver {
@@ -21,12 +38,77 @@ impl<B: BExp> Solver<B> {
             dead_states: HashSet::default(),
             explored: HashSet::default(),
             uf_table: HashMap::default(),
+            related: None,
+            hyps: Hypotheses::new(),
+            deadline: None,
+            max_pairs: None,
+            stopped: None,
+            stats: SolverStats::default(),
             // caching
             eps_cache: HashMap::default(),
             drv_cache: HashMap::default(),
         }
     }
 
+    // Decide equivalence modulo Hoare hypotheses on actions.
+    pub fn set_hypotheses(&mut self, hyps: Hypotheses<B>) {
+        self.hyps = hyps;
+        self.drv_cache.clear();
+        self.dead_states.clear();
+        self.uf_table.clear();
+    }
+
+    // Record the pairs equiv_iter relates, which certificate exports.
+    pub fn record_relation(&mut self) {
+        self.related = Some(vec![]);
+    }
+
+    #[inline]
+    pub fn relate(&mut self, exp1: &Exp<B>, exp2: &Exp<B>) {
+        if let Some(related) = &mut self.related {
+            related.push((exp1.clone(), exp2.clone()));
+        }
+    }
+
+    // None unless record_relation was called.
+    pub fn relation(&self) -> Option<&[(Exp<B>, Exp<B>)]> {
+        self.related.as_deref()
+    }
+
+    // Bound the search; the gkat enforces its own limits.
+    pub fn set_limits(&mut self, limits: &Limits) {
+        self.deadline = limits.deadline();
+        self.max_pairs = limits.max_pairs;
+        self.stopped = None;
+    }
+
+    // Limit that stopped the search or the gkat, making results meaningless.
+    pub fn limit_hit<G: Gkat<B>>(&self, gkat: &G) -> Option<Limit> {
+        self.stopped.or(gkat.exhausted())
+    }
+
+    // Result of a search, unless a limit cut it short.
+    pub fn verdict<G: Gkat<B>, T>(&self, gkat: &G, result: T) -> Result<T, Limit> {
+        match self.limit_hit(gkat) {
+            Some(limit) => Err(limit),
+            None => Ok(result),
+        }
+    }
+
+    pub fn out_of_budget<G: Gkat<B>>(&mut self, gkat: &G) -> bool {
+        if self.max_pairs.is_some_and(|n| self.stats.pairs > n) {
+            self.stopped.get_or_insert(Limit::Pairs);
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.stopped.get_or_insert(Limit::Timeout);
+        }
+        self.limit_hit(gkat).is_some()
+    }
+
+    #[inline]
+    pub fn get_post(&self, a: u64) -> Option<&Vec<(B, B)>> {
+        self.hyps.get(a)
+    }
+
     pub fn get_uf(&mut self, exp: &Exp<B>) -> UnionFindNode<()> {
         match self.uf_table.get(exp) {
             Some(node) => node.clone(),
@@ -40,7 +122,12 @@ impl<B: BExp> Solver<B> {
 
     #[inline]
     pub fn get_eps(&mut self, exp: &Exp<B>) -> Option<&B> {
-        self.eps_cache.get(exp)
+        let eps = self.eps_cache.get(exp);
+        match eps {
+            Some(_) => self.stats.eps_hits += 1,
+            None => self.stats.eps_misses += 1,
+        }
+        eps
     }
 
     #[inline]
@@ -50,7 +137,12 @@ impl<B: BExp> Solver<B> {
 
     #[inline]
     pub fn get_drv(&mut self, exp: &Exp<B>) -> Option<&Deriv<B>> {
-        self.drv_cache.get(exp)
+        let deriv = self.drv_cache.get(exp);
+        match deriv {
+            Some(_) => self.stats.drv_hits += 1,
+            None => self.stats.drv_misses += 1,
+        }
+        deriv
     }
 
     #[inline]
@@ -67,18 +159,27 @@ impl<B: BExp> Solver<B> {
 
     #[inline]
     pub fn known_dead(&self, exp: &Exp<B>) -> bool {
-        self.dead_states.contains(&exp)
+        self.dead_states.contains(exp)
     }
 
-    pub fn is_dead<G: Gkat<B>>(&mut self, gkat: &mut G, exp: &Exp<B>) -> bool {
+    // Whether exp accepts no guarded string.
+    pub fn is_dead<G: Gkat<B>>(&mut self, gkat: &mut G, exp: &Exp<B>) -> Result<bool, Limit> {
+        let dead = self.dead(gkat, exp);
+        self.verdict(gkat, dead)
+    }
+
+    // is_dead for searches, which check the limits themselves.
+    pub(super) fn dead<G: Gkat<B>>(&mut self, gkat: &mut G, exp: &Exp<B>) -> bool {
         let mut stack = Vec::new();
         stack.push(exp.clone());
         self.explored.clear();
+        self.stats.is_dead_calls += 1;
         while let Some(exp) = stack.pop() {
             if self.known_dead(&exp) || self.explored.contains(&exp) {
                 continue;
             }
             self.explored.insert(exp.clone());
+            self.stats.dead_explored += 1;
             let eps = self.epsilon(gkat, &exp);
             if gkat.is_false(&eps) {
                 for (_, e, _) in self.derivative(gkat, &exp) {
@@ -88,7 +189,10 @@ impl<B: BExp> Solver<B> {
                 return false;
             }
         }
-        self.dead_states.extend(self.explored.iter().cloned());
-        return true;
+        // states explored under an exhausted gkat may not be dead
+        if self.limit_hit(gkat).is_none() {
+            self.dead_states.extend(self.explored.iter().cloned());
+        }
+        true
     }
 }
@@ -0,0 +1,113 @@
+use super::*;
+use crate::hash::{HashMap, HashMapExt};
+use std::collections::VecDeque;
+
+// Predecessor of a state on a shortest path, with the guard and action
+// that lead from it.
+type Step<B> = (Exp<B>, B, u64);
+
+impl<B: BExp> Solver<B> {
+    pub fn equiv_witness<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp1: &Exp<B>,
+        exp2: &Exp<B>,
+    ) -> Result<Option<Witness>, Limit> {
+        let Some(mismatch) = self.equiv_search(gkat, exp1, exp2)? else {
+            return Ok(None);
+        };
+        // the verdict is in, so build the witness without limits
+        gkat.set_limits(&Limits::default());
+        let w = self.mk_witness(gkat, mismatch);
+        assert!(self.accepts(gkat, exp1, &w.string) == w.left_accepts);
+        assert!(self.accepts(gkat, exp2, &w.string) != w.left_accepts);
+        Ok(Some(w))
+    }
+
+    pub fn mk_witness<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        mismatch: Mismatch<B, Exp<B>>,
+    ) -> Witness {
+        let mut steps = mismatch.path;
+        let last = match mismatch.suffix {
+            None => mismatch.guard,
+            Some((a, exp)) => {
+                steps.push((mismatch.guard, a));
+                let (live_steps, eps) = self.live_path(gkat, &exp).unwrap();
+                steps.extend(live_steps);
+                eps
+            }
+        };
+        Witness {
+            string: mk_guarded_string(gkat, &steps, &last),
+            left_accepts: mismatch.left_accepts,
+        }
+    }
+
+    // Shortest symbolic path from exp to acceptance, if exp is not dead.
+    pub fn live_path<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        exp: &Exp<B>,
+    ) -> Option<(Vec<(B, u64)>, B)> {
+        let mut parents: HashMap<Exp<B>, Option<Step<B>>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        parents.insert(exp.clone(), None);
+        queue.push_back(exp.clone());
+        while let Some(exp) = queue.pop_front() {
+            let eps = self.epsilon(gkat, &exp);
+            if !gkat.is_false(&eps) {
+                let mut steps = vec![];
+                let mut cur = exp;
+                while let Some(Some((prev, b, a))) = parents.get(&cur) {
+                    steps.push((b.clone(), *a));
+                    cur = prev.clone();
+                }
+                steps.reverse();
+                return Some((steps, eps));
+            }
+            for (b, next, a) in self.derivative(gkat, &exp) {
+                if !parents.contains_key(&next) {
+                    parents.insert(next.clone(), Some((exp.clone(), b, a)));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    // Run a guarded string through the derivatives of exp.
+    pub fn accepts<G: Gkat<B>>(&mut self, gkat: &mut G, exp: &Exp<B>, s: &GuardedString) -> bool {
+        let mut exp = exp.clone();
+        for (atom, act) in s.atoms.iter().zip(s.acts.iter()) {
+            let at = gkat.mk_atom(atom);
+            let next = self.derivative(gkat, &exp).into_iter().find(|(b, _, _)| {
+                let ab = gkat.mk_and(&at, b);
+                !gkat.is_false(&ab)
+            });
+            match next {
+                Some((_, e, p)) if gkat.act_name(p) == *act => exp = e,
+                _ => return false,
+            }
+        }
+        let at = gkat.mk_atom(s.atoms.last().unwrap());
+        let eps = self.epsilon(gkat, &exp);
+        let b = gkat.mk_and(&at, &eps);
+        !gkat.is_false(&b)
+    }
+}
+
+#[test]
+pub fn test() {
+    use crate::parsing::spec::ExpParser;
+    for (e1, e2) in WITNESS_CASES {
+        let mut gkat = PureBDDGkat::new();
+        let mut solver = Solver::new();
+        let exp1 = gkat.from_exp(ExpParser::new().parse(e1).unwrap());
+        let exp2 = gkat.from_exp(ExpParser::new().parse(e2).unwrap());
+        let w = solver.equiv_witness(&mut gkat, &exp1, &exp2).unwrap().unwrap();
+        assert_eq!(solver.accepts(&mut gkat, &exp1, &w.string), w.left_accepts);
+        assert_eq!(solver.accepts(&mut gkat, &exp2, &w.string), !w.left_accepts);
+    }
+}
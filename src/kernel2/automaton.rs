@@ -1,5 +1,5 @@
 use super::*;
-use gxhash::{HashMap, HashMapExt};
+use crate::hash::{HashMap, HashMapExt};
 use recursive::recursive;
 
 struct RawAutomaton<B> {
@@ -29,9 +29,12 @@ impl<B: BExp> Solver<B> {
         eps_hat.insert(st, eps_star);
         delta_hat.insert(st, delta_star);
         let automaton = Automaton {
-            eps_hat: eps_hat,
-            delta_hat: delta_hat,
+            eps_hat,
+            delta_hat,
         };
+        let (states, transitions) = automaton.size();
+        self.stats.states += states;
+        self.stats.transitions += transitions;
         (st, automaton)
     }
 
@@ -40,23 +43,31 @@ impl<B: BExp> Solver<B> {
         use Exp_::*;
         match m.get() {
             Act(a) => {
-                let st = self.new_state();
+                // one state per postcondition of a
+                let post = match self.get_post(*a) {
+                    Some(post) => post.clone(),
+                    None => vec![(gkat.mk_one(), gkat.mk_one())],
+                };
                 // eps_star
                 let eps_star = gkat.mk_zero();
-                // delta_star
-                let delta_star = vec![(gkat.mk_one(), st, *a)];
-                // eps_hat
+                let mut delta_star = vec![];
                 let mut eps_hat = HashMap::new();
-                eps_hat.insert(st, gkat.mk_one());
-                // delta_hat
                 let mut delta_hat = HashMap::new();
-                delta_hat.insert(st, vec![]);
+                for (b, c) in post {
+                    let st = self.new_state();
+                    // delta_star
+                    delta_star.push((b, st, *a));
+                    // eps_hat
+                    eps_hat.insert(st, c);
+                    // delta_hat
+                    delta_hat.insert(st, vec![]);
+                }
                 // raw_automaton
                 RawAutomaton {
-                    eps_star: eps_star,
-                    delta_star: delta_star,
-                    eps_hat: eps_hat,
-                    delta_hat: delta_hat,
+                    eps_star,
+                    delta_star,
+                    eps_hat,
+                    delta_hat,
                 }
             }
             Seq(p1, p2) => {
@@ -84,10 +95,10 @@ impl<B: BExp> Solver<B> {
                 delta_hat.extend(r2.delta_hat);
                 // raw_automaton
                 RawAutomaton {
-                    eps_star: eps_star,
-                    delta_star: delta_star,
-                    eps_hat: eps_hat,
-                    delta_hat: delta_hat,
+                    eps_star,
+                    delta_star,
+                    eps_hat,
+                    delta_hat,
                 }
             }
             Ifte(b, p1, p2) => {
@@ -111,10 +122,10 @@ impl<B: BExp> Solver<B> {
                 delta_hat.extend(r2.delta_hat);
                 // raw_automaton
                 RawAutomaton {
-                    eps_star: eps_star,
-                    delta_star: delta_star,
-                    eps_hat: eps_hat,
-                    delta_hat: delta_hat,
+                    eps_star,
+                    delta_star,
+                    eps_hat,
+                    delta_hat,
                 }
             }
             Test(b) => RawAutomaton {
@@ -145,10 +156,10 @@ impl<B: BExp> Solver<B> {
                 }
                 // raw_automaton
                 RawAutomaton {
-                    eps_star: eps_star,
-                    delta_star: delta_star,
-                    eps_hat: eps_hat,
-                    delta_hat: delta_hat,
+                    eps_star,
+                    delta_star,
+                    eps_hat,
+                    delta_hat,
                 }
             }
         }
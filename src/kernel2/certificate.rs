@@ -0,0 +1,37 @@
+use super::*;
+use crate::parsing::{CertState, Certificate};
+
+impl<B: BExp> Solver<B> {
+    // Export both automata and the relation built by equiv_iter, or None
+    // unless it ran after record_relation.
+    pub fn certificate<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        i: u64,
+        j: u64,
+        m: &Automaton<B>,
+        n: &Automaton<B>,
+    ) -> Option<Certificate> {
+        let pairs = self.relation()?.to_vec();
+        let mut ids: Vec<_> = m.eps_hat.keys().chain(n.eps_hat.keys()).cloned().collect();
+        ids.sort();
+        let states = ids.into_iter().map(|st| {
+            let a = if m.eps_hat.contains_key(&st) { m } else { n };
+            let eps = a.eps_hat.get(&st).unwrap();
+            let trans = a.delta_hat.get(&st).unwrap().iter();
+            CertState {
+                id: st,
+                eps: gkat.to_bexp(eps),
+                trans: trans
+                    .map(|(b, next, act)| (gkat.to_bexp(b), gkat.act_name(*act), *next))
+                    .collect(),
+            }
+        });
+        Some(Certificate {
+            start: (i, j),
+            states: states.collect(),
+            exps: vec![],
+            pairs,
+        })
+    }
+}
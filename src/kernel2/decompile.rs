@@ -0,0 +1,828 @@
+use super::*;
+use crate::hash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use std::{fmt, rc::Rc};
+
+// Program fragment whose jumps to other states only occur in tail position.
+enum Node<B> {
+    Accept,
+    Reject,
+    Jump(u64),
+    Branch(B, Tree<B>, Tree<B>),
+    Act(u64, Tree<B>),
+    // while c do body; then continue with the tree
+    Loop(B, Exp<B>, Tree<B>),
+}
+
+struct TreeNode<B> {
+    node: Node<B>,
+    // states reachable by a jump, sorted
+    jumps: Vec<u64>,
+    // whether some path ends in Accept
+    accepts: bool,
+}
+
+type Tree<B> = Rc<TreeNode<B>>;
+
+// Why decompile has no expression for an automaton.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecompileError {
+    // loops over these states cannot be written with while
+    NotExpressible(Vec<u64>),
+    // the expression found is not equivalent to the automaton
+    RoundTrip,
+    // a limit stopped the round-trip check
+    Unknown(Limit),
+}
+
+impl fmt::Display for DecompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompileError::NotExpressible(states) => {
+                let states: Vec<_> = states.iter().map(|st| st.to_string()).collect();
+                write!(
+                    f,
+                    "not expressible in GKAT: states {} are not well-nested",
+                    states.join(", ")
+                )
+            }
+            DecompileError::RoundTrip => {
+                write!(f, "decompiled expression is not equivalent to the automaton")
+            }
+            DecompileError::Unknown(limit) => write!(f, "unknown: {}", limit),
+        }
+    }
+}
+
+fn mk<B>(node: Node<B>) -> Tree<B> {
+    use Node::*;
+    let (jumps, accepts) = match &node {
+        Accept => (vec![], true),
+        Reject => (vec![], false),
+        Jump(st) => (vec![*st], false),
+        Branch(_, t1, t2) => {
+            let mut jumps = t1.jumps.clone();
+            jumps.extend(t2.jumps.iter());
+            jumps.sort();
+            jumps.dedup();
+            (jumps, t1.accepts || t2.accepts)
+        }
+        Act(_, t) | Loop(_, _, t) => (t.jumps.clone(), t.accepts),
+    };
+    Rc::new(TreeNode {
+        node,
+        jumps,
+        accepts,
+    })
+}
+
+impl<B> TreeNode<B> {
+    fn has_jump(&self, st: u64) -> bool {
+        self.jumps.binary_search(&st).is_ok()
+    }
+
+    // Continuation that can only return to st or fail.
+    fn returns_to(&self, st: u64) -> bool {
+        !self.accepts && self.jumps.iter().all(|j| *j == st)
+    }
+}
+
+// Tree of the first step of a state.
+fn mk_tree<B: BExp, G: Gkat<B>>(gkat: &mut G, eps: &B, delta: &[(B, u64, u64)]) -> Tree<B> {
+    let tree = delta
+        .iter()
+        .rev()
+        .fold(mk(Node::Reject), |acc, (b, next, a)| {
+            let t = mk(Node::Act(*a, mk(Node::Jump(*next))));
+            mk(Node::Branch(b.clone(), t, acc))
+        });
+    if gkat.is_false(eps) {
+        tree
+    } else {
+        mk(Node::Branch(eps.clone(), mk(Node::Accept), tree))
+    }
+}
+
+// Replace every jump to a state of sub by its tree.
+fn subst<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    tree: &Tree<B>,
+    sub: &HashMap<u64, Tree<B>>,
+    memo: &mut HashMap<*const TreeNode<B>, Tree<B>>,
+) -> Tree<B> {
+    use Node::*;
+    if !tree.jumps.iter().any(|j| sub.contains_key(j)) {
+        return tree.clone();
+    }
+    if let Some(res) = memo.get(&Rc::as_ptr(tree)) {
+        return res.clone();
+    }
+    let res = match &tree.node {
+        Jump(st) => sub.get(st).unwrap().clone(),
+        Branch(b, t1, t2) => {
+            let t1 = subst(gkat, t1, sub, memo);
+            let t2 = subst(gkat, t2, sub, memo);
+            mk(Branch(b.clone(), t1, t2))
+        }
+        Act(a, t1) => mk(Act(*a, subst(gkat, t1, sub, memo))),
+        Loop(c, body, t1) => {
+            let t1 = subst(gkat, t1, sub, memo);
+            let nc = gkat.mk_not(c);
+            mk(Loop(c.clone(), body.clone(), restrict(gkat, &t1, &nc)))
+        }
+        Accept | Reject => unreachable!(),
+    };
+    memo.insert(Rc::as_ptr(tree), res.clone());
+    res
+}
+
+// Drop the branches of first steps that are impossible under guard.
+fn restrict<B: BExp, G: Gkat<B>>(gkat: &mut G, tree: &Tree<B>, guard: &B) -> Tree<B> {
+    use Node::*;
+    match &tree.node {
+        Branch(b, t1, t2) => {
+            let g1 = gkat.mk_and(guard, b);
+            let nb = gkat.mk_not(b);
+            let g2 = gkat.mk_and(guard, &nb);
+            if gkat.is_false(&g1) {
+                restrict(gkat, t2, &g2)
+            } else if gkat.is_false(&g2) {
+                restrict(gkat, t1, &g1)
+            } else {
+                let t1 = restrict(gkat, t1, &g1);
+                let t2 = restrict(gkat, t2, &g2);
+                mk(Branch(b.clone(), t1, t2))
+            }
+        }
+        Loop(c, _, t) => {
+            let gc = gkat.mk_and(guard, c);
+            if gkat.is_false(&gc) {
+                restrict(gkat, t, guard)
+            } else {
+                tree.clone()
+            }
+        }
+        _ => tree.clone(),
+    }
+}
+
+// Collect the guard of first steps that continue into a loop on st.
+fn loop_guard<B: BExp, G: Gkat<B>>(gkat: &mut G, tree: &Tree<B>, st: u64, guard: &B) -> B {
+    use Node::*;
+    match &tree.node {
+        Branch(b, t1, t2) => {
+            let nb = gkat.mk_not(b);
+            let g1 = gkat.mk_and(guard, b);
+            let g2 = gkat.mk_and(guard, &nb);
+            let c1 = loop_guard(gkat, t1, st, &g1);
+            let c2 = loop_guard(gkat, t2, st, &g2);
+            gkat.mk_or(&c1, &c2)
+        }
+        Act(_, t) if t.has_jump(st) => guard.clone(),
+        Loop(c, _, t) => {
+            let nc = gkat.mk_not(c);
+            let g2 = gkat.mk_and(guard, &nc);
+            let c2 = loop_guard(gkat, t, st, &g2);
+            if t.has_jump(st) {
+                let g1 = gkat.mk_and(guard, c);
+                gkat.mk_or(&g1, &c2)
+            } else {
+                c2
+            }
+        }
+        _ => gkat.mk_zero(),
+    }
+}
+
+// One iteration of the loop on st: first steps leaving the loop fail.
+fn loop_body<B: BExp, G: Gkat<B>>(gkat: &mut G, tree: &Tree<B>, st: u64) -> Tree<B> {
+    use Node::*;
+    let mut sub = HashMap::new();
+    sub.insert(st, mk(Accept));
+    match &tree.node {
+        Branch(b, t1, t2) => {
+            let t1 = loop_body(gkat, t1, st);
+            let t2 = loop_body(gkat, t2, st);
+            mk(Branch(b.clone(), t1, t2))
+        }
+        Act(a, t) if t.has_jump(st) => mk(Act(*a, subst(gkat, t, &sub, &mut HashMap::new()))),
+        Loop(c, body, t) => {
+            let t1 = if t.has_jump(st) {
+                let t = subst(gkat, t, &sub, &mut HashMap::new());
+                mk(Loop(c.clone(), body.clone(), t))
+            } else {
+                mk(Reject)
+            };
+            mk(Branch(c.clone(), t1, loop_body(gkat, t, st)))
+        }
+        _ => mk(Reject),
+    }
+}
+
+// Behavior after the loop on st: first steps entering the loop fail.
+fn loop_exit<B: Clone>(tree: &Tree<B>, st: u64) -> Tree<B> {
+    use Node::*;
+    if !tree.has_jump(st) {
+        return tree.clone();
+    }
+    match &tree.node {
+        Branch(b, t1, t2) => mk(Branch(b.clone(), loop_exit(t1, st), loop_exit(t2, st))),
+        Loop(c, body, t) => {
+            let t1 = if t.has_jump(st) {
+                mk(Reject)
+            } else {
+                mk(Loop(c.clone(), body.clone(), t.clone()))
+            };
+            mk(Branch(c.clone(), t1, loop_exit(t, st)))
+        }
+        _ => mk(Reject),
+    }
+}
+
+// Every first step continuing into the loop on st must only return to st.
+fn loop_guard_pure<B>(tree: &Tree<B>, st: u64) -> bool {
+    use Node::*;
+    match &tree.node {
+        Branch(_, t1, t2) => loop_guard_pure(t1, st) && loop_guard_pure(t2, st),
+        Act(_, t) => !t.has_jump(st) || t.returns_to(st),
+        Loop(_, _, t) => (!t.has_jump(st) || t.returns_to(st)) && loop_guard_pure(t, st),
+        Accept | Reject | Jump(_) => true,
+    }
+}
+
+fn close_loop<B: BExp, G: Gkat<B>>(gkat: &mut G, tree: &Tree<B>, st: u64) -> Tree<B> {
+    let one = gkat.mk_one();
+    let c = loop_guard(gkat, tree, st, &one);
+    let body = loop_body(gkat, tree, st);
+    let body = to_exp(gkat, &body, &c, &mut HashMap::new());
+    let nc = gkat.mk_not(&c);
+    let exit = restrict(gkat, &loop_exit(tree, st), &nc);
+    mk(Node::Loop(c, body, exit))
+}
+
+// Expression of tree, leaving out the tests that guard already decides.
+fn to_exp<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    tree: &Tree<B>,
+    guard: &B,
+    memo: &mut HashMap<(*const TreeNode<B>, B), Exp<B>>,
+) -> Exp<B> {
+    use Node::*;
+    let key = (Rc::as_ptr(tree), guard.clone());
+    if let Some(exp) = memo.get(&key) {
+        return exp.clone();
+    }
+    let one = gkat.mk_one();
+    let exp = match &tree.node {
+        Accept => gkat.mk_test(one),
+        Reject | Jump(_) => gkat.mk_fail(),
+        Branch(b, t1, t2) => {
+            let g1 = gkat.mk_and(guard, b);
+            let nb = gkat.mk_not(b);
+            let g2 = gkat.mk_and(guard, &nb);
+            if gkat.is_false(&g1) {
+                to_exp(gkat, t2, &g2, memo)
+            } else if gkat.is_false(&g2) {
+                to_exp(gkat, t1, &g1, memo)
+            } else {
+                let p1 = to_exp(gkat, t1, &g1, memo);
+                let p2 = to_exp(gkat, t2, &g2, memo);
+                gkat.mk_ifte(b.clone(), p1, p2)
+            }
+        }
+        Act(a, t) => {
+            let p1 = gkat.hashcons(Exp_::Act(*a));
+            let p2 = to_exp(gkat, t, &one, memo);
+            gkat.mk_seq(p1, p2)
+        }
+        Loop(c, body, t) => {
+            let p1 = gkat.mk_while(c.clone(), body.clone());
+            let nc = gkat.mk_not(c);
+            let p2 = to_exp(gkat, t, &nc, memo);
+            gkat.mk_seq(p1, p2)
+        }
+    };
+    memo.insert(key, exp.clone());
+    exp
+}
+
+// Where states of a loop jump to its header instead.
+#[derive(Debug)]
+enum Fold {
+    // every first step the header takes as well, of states that leave the
+    // loop like the header
+    Leaving,
+    // every first step the header takes as well
+    Same,
+    // first steps leaving the loop that the header takes as well
+    Exits,
+}
+
+// Trees of the states of a minimized automaton, rewritten until their
+// jumps no longer form cycles.
+struct Trees<B> {
+    m: Automaton<B>,
+    trees: HashMap<u64, Tree<B>>,
+    // guard under which a state was redirected to a loop header
+    folded: HashMap<u64, B>,
+    // first free state for headers that are not states of m
+    fresh: u64,
+    // loops that may still be nested in the ones being closed, one per
+    // state of m, since synthesized headers could nest without end
+    depth: usize,
+}
+
+impl<B: BExp> Trees<B> {
+    // Close every cycle among states with a while loop.
+    fn structure<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        states: &[u64],
+    ) -> Result<(), DecompileError> {
+        let sccs = self.cyclic(states);
+        let cyclic: HashSet<u64> = sccs.iter().flatten().cloned().collect();
+        // outer loops first, so that loops after them are still open when
+        // they turn out to belong to their body
+        for outer in sccs.iter().rev() {
+            for scc in self.cyclic(outer) {
+                if !self.close_any(gkat, &scc, states, &cyclic) {
+                    return Err(DecompileError::NotExpressible(scc));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Try the headers of the loop scc in turn: its states, the states
+    // entering it, and new states that behave like one of those except
+    // that they also leave the loop the way its states do.
+    fn close_any<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        scc: &[u64],
+        states: &[u64],
+        cyclic: &HashSet<u64>,
+    ) -> bool {
+        let entries: Vec<u64> = states
+            .iter()
+            .filter(|i| {
+                let jumps = &self.trees.get(i).unwrap().jumps;
+                !cyclic.contains(i) && jumps.iter().any(|j| scc.contains(j))
+            })
+            .cloned()
+            .collect();
+        let bases: Vec<u64> = scc.iter().chain(entries.iter()).cloned().collect();
+        // states after the loop may be part of its body that only leaves
+        // through the header
+        let below = self.below(scc, states);
+        let folds = [Fold::Same, Fold::Exits, Fold::Leaving];
+        let mut layouts: Vec<_> = folds.iter().map(|fold| (false, fold)).collect();
+        if !below.is_empty() {
+            layouts.extend(folds.iter().map(|fold| (true, fold)));
+        }
+        for synth in [false, true] {
+            for (after, fold) in &layouts {
+                for base in &bases {
+                    let h = if !synth {
+                        *base
+                    } else {
+                        match self.synthesize(gkat, *base, scc) {
+                            Some(h) => h,
+                            None => continue,
+                        }
+                    };
+                    let mut members = scc.to_vec();
+                    for i in [*base, h] {
+                        if !members.contains(&i) {
+                            members.push(i);
+                        }
+                    }
+                    if *after {
+                        let body = self.body(gkat, h, &members, &below);
+                        members.extend(body);
+                    }
+                    let saved: Vec<_> = members
+                        .iter()
+                        .map(|i| (*i, self.trees[i].clone(), self.folded.get(i).cloned()))
+                        .collect();
+                    if self.close(gkat, &members, h, fold) {
+                        return true;
+                    }
+                    for (i, tree, folded) in saved {
+                        self.trees.insert(i, tree);
+                        match folded {
+                            Some(b) => self.folded.insert(i, b),
+                            None => self.folded.remove(&i),
+                        };
+                    }
+                    if synth {
+                        self.trees.remove(&h);
+                        self.m.eps_hat.remove(&h);
+                        self.m.delta_hat.remove(&h);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    // New state that behaves like base, and like the states of scc leaving
+    // it where base fails.
+    fn synthesize<G: Gkat<B>>(&mut self, gkat: &mut G, base: u64, scc: &[u64]) -> Option<u64> {
+        let mut eps = self.m.eps_hat[&base].clone();
+        let mut delta = self.m.delta_hat[&base].clone();
+        let mut covered = delta.iter().fold(eps.clone(), |acc, (b, _, _)| gkat.mk_or(&acc, b));
+        let mut grown = false;
+        for i in scc {
+            let exits = self.m.delta_hat[i].iter().filter(|(_, next, _)| !scc.contains(next));
+            let mut steps = vec![(self.m.eps_hat[i].clone(), None)];
+            steps.extend(exits.map(|(b, next, a)| (b.clone(), Some((*next, *a)))));
+            for (b, step) in steps {
+                let ncovered = gkat.mk_not(&covered);
+                let b = gkat.mk_and(&b, &ncovered);
+                if gkat.is_false(&b) {
+                    continue;
+                }
+                covered = gkat.mk_or(&covered, &b);
+                grown = true;
+                match step {
+                    Some((next, a)) => delta.push((b, next, a)),
+                    None => eps = gkat.mk_or(&eps, &b),
+                }
+            }
+        }
+        if !grown {
+            return None;
+        }
+        let h = self.fresh;
+        self.fresh += 1;
+        self.trees.insert(h, mk_tree(gkat, &eps, &delta));
+        self.m.eps_hat.insert(h, eps);
+        self.m.delta_hat.insert(h, delta);
+        Some(h)
+    }
+
+    // Strongly connected components of states with a cycle.
+    fn cyclic(&self, states: &[u64]) -> Vec<Vec<u64>> {
+        let sccs = self.sccs(states).into_iter();
+        sccs.filter(|scc| scc.len() > 1 || self.trees.get(&scc[0]).unwrap().has_jump(scc[0]))
+            .collect()
+    }
+
+    // States outside scc reachable from it without leaving states.
+    fn below(&self, scc: &[u64], states: &[u64]) -> Vec<u64> {
+        let within: HashSet<u64> = states.iter().cloned().collect();
+        let mut seen: HashSet<u64> = scc.iter().cloned().collect();
+        let mut stack = scc.to_vec();
+        let mut below = vec![];
+        while let Some(i) = stack.pop() {
+            for j in &self.trees.get(&i).unwrap().jumps {
+                if within.contains(j) && seen.insert(*j) {
+                    below.push(*j);
+                    stack.push(*j);
+                }
+            }
+        }
+        below.sort();
+        below
+    }
+
+    // Turn the states returning to h after folding members onto it into a
+    // while loop with header h. Members first jump to h where they behave
+    // like it, so that copies of the header inside the loop body become
+    // its back edges.
+    fn close<G: Gkat<B>>(&mut self, gkat: &mut G, members: &[u64], h: u64, fold: &Fold) -> bool {
+        for i in members.iter().filter(|i| **i != h) {
+            match fold {
+                Fold::Same => self.fold(gkat, *i, h, &[]),
+                Fold::Exits => self.fold(gkat, *i, h, members),
+                Fold::Leaving => {
+                    let leaving = self.same(gkat, *i, h, members);
+                    if !gkat.is_false(&leaving) {
+                        self.fold(gkat, *i, h, &[]);
+                    }
+                }
+            }
+        }
+        let scc = self.sccs(members).into_iter().find(|scc| scc.contains(&h));
+        let rest: Vec<u64> = scc.unwrap().into_iter().filter(|i| *i != h).collect();
+        if self.depth == 0 {
+            return false;
+        }
+        self.depth -= 1;
+        let structured = self.structure(gkat, &rest);
+        self.depth += 1;
+        if structured.is_err() {
+            return false;
+        }
+        let within = rest.into_iter().collect();
+        let tree = self.trees.get(&h).unwrap().clone();
+        let tree = self.inline(gkat, &tree, &within, &mut HashMap::new());
+        if !tree.has_jump(h) || !loop_guard_pure(&tree, h) {
+            return false;
+        }
+        let tree = close_loop(gkat, &tree, h);
+        self.trees.insert(h, tree);
+        true
+    }
+
+    // Redirect state i to h on the first steps where both do the same.
+    fn fold<G: Gkat<B>>(&mut self, gkat: &mut G, i: u64, h: u64, inside: &[u64]) {
+        let same = self.same(gkat, i, h, inside);
+        let done = self.done(gkat, i);
+        if gkat.is_false(&same) {
+            return;
+        }
+        let nsame = gkat.mk_not(&same);
+        let tree = restrict(gkat, self.trees.get(&i).unwrap(), &nsame);
+        let tree = mk(Node::Branch(same.clone(), mk(Node::Jump(h)), tree));
+        self.trees.insert(i, tree);
+        let done = gkat.mk_or(&done, &same);
+        self.folded.insert(i, done);
+    }
+
+    // Guard of the first steps of i, not yet redirected, that h takes as
+    // well, ignoring steps into inside.
+    fn same<G: Gkat<B>>(&mut self, gkat: &mut G, i: u64, h: u64, inside: &[u64]) -> B {
+        let mut same = gkat.mk_and(&self.m.eps_hat[&i], &self.m.eps_hat[&h]);
+        for (b1, next1, a1) in &self.m.delta_hat[&i] {
+            for (b2, next2, a2) in &self.m.delta_hat[&h] {
+                if next1 == next2 && a1 == a2 && !inside.contains(next1) {
+                    let b = gkat.mk_and(b1, b2);
+                    same = gkat.mk_or(&same, &b);
+                }
+            }
+        }
+        let done = self.done(gkat, i);
+        let ndone = gkat.mk_not(&done);
+        gkat.mk_and(&same, &ndone)
+    }
+
+    fn done<G: Gkat<B>>(&self, gkat: &mut G, i: u64) -> B {
+        match self.folded.get(&i) {
+            Some(b) => b.clone(),
+            None => gkat.mk_zero(),
+        }
+    }
+
+    // States of below that leave core and themselves only where they
+    // behave like h, so that they can belong to the body of its loop.
+    fn body<G: Gkat<B>>(&mut self, gkat: &mut G, h: u64, core: &[u64], below: &[u64]) -> Vec<u64> {
+        let mut body = below.to_vec();
+        loop {
+            let mut members = core.to_vec();
+            members.extend(body.iter());
+            let kept: Vec<u64> = body
+                .iter()
+                .filter(|i| {
+                    let mut exits = self.m.eps_hat[i].clone();
+                    for (b, next, _) in &self.m.delta_hat[i] {
+                        if !members.contains(next) {
+                            exits = gkat.mk_or(&exits, b);
+                        }
+                    }
+                    let same = self.same(gkat, **i, h, &members);
+                    let nsame = gkat.mk_not(&same);
+                    let done = self.done(gkat, **i);
+                    let ndone = gkat.mk_not(&done);
+                    let rest = gkat.mk_and(&exits, &nsame);
+                    let rest = gkat.mk_and(&rest, &ndone);
+                    gkat.is_false(&rest)
+                })
+                .cloned()
+                .collect();
+            if kept.len() == body.len() {
+                return body;
+            }
+            body = kept;
+        }
+    }
+
+    // Replace the jumps of tree to states within, whose jumps among each
+    // other must be acyclic, by their trees.
+    fn inline<G: Gkat<B>>(
+        &self,
+        gkat: &mut G,
+        tree: &Tree<B>,
+        within: &HashSet<u64>,
+        done: &mut HashMap<u64, Tree<B>>,
+    ) -> Tree<B> {
+        let mut sub = HashMap::new();
+        for j in tree.jumps.iter().filter(|j| within.contains(j)) {
+            let t = match done.get(j) {
+                Some(t) => t.clone(),
+                None => {
+                    let t = self.inline(gkat, self.trees.get(j).unwrap(), within, done);
+                    done.insert(*j, t.clone());
+                    t
+                }
+            };
+            sub.insert(*j, t);
+        }
+        subst(gkat, tree, &sub, &mut HashMap::new())
+    }
+
+    // Strongly connected components of the jumps among states (Tarjan).
+    fn sccs(&self, states: &[u64]) -> Vec<Vec<u64>> {
+        let within: HashSet<u64> = states.iter().cloned().collect();
+        let mut index: HashMap<u64, usize> = HashMap::new();
+        let mut low: HashMap<u64, usize> = HashMap::new();
+        let mut stack = vec![];
+        let mut on_stack = HashSet::new();
+        let mut sccs = vec![];
+        for st in states {
+            if index.contains_key(st) {
+                continue;
+            }
+            let mut work = vec![(*st, 0)];
+            while let Some((i, k)) = work.pop() {
+                if k == 0 {
+                    index.insert(i, index.len());
+                    low.insert(i, index[&i]);
+                    stack.push(i);
+                    on_stack.insert(i);
+                }
+                let jumps = &self.trees.get(&i).unwrap().jumps;
+                let next = jumps[k..]
+                    .iter()
+                    .position(|j| within.contains(j) && !index.contains_key(j));
+                for j in &jumps[k..k + next.unwrap_or(jumps.len() - k)] {
+                    if on_stack.contains(j) {
+                        low.insert(i, low[&i].min(index[j]));
+                    }
+                }
+                if let Some(n) = next {
+                    work.push((i, k + n + 1));
+                    work.push((jumps[k + n], 0));
+                    continue;
+                }
+                if low[&i] == index[&i] {
+                    let mut scc = vec![];
+                    loop {
+                        let j = stack.pop().unwrap();
+                        on_stack.remove(&j);
+                        scc.push(j);
+                        if j == i {
+                            break;
+                        }
+                    }
+                    scc.sort();
+                    sccs.push(scc);
+                }
+                if let Some((parent, _)) = work.last() {
+                    low.insert(*parent, low[parent].min(low[&i]));
+                }
+            }
+        }
+        sccs
+    }
+}
+
+impl<B: BExp> Solver<B> {
+    // Recover an expression for the automaton starting from st. Every
+    // cycle is closed into a while loop at some state of it; loops that
+    // cannot be given such a header are not expressible. The expression
+    // is checked to be equivalent to the automaton.
+    pub fn decompile<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        st: u64,
+        m: &Automaton<B>,
+    ) -> Result<Exp<B>, DecompileError> {
+        let exp = self.decompile_unchecked(gkat, st, m)?;
+        let mut check = self.fork(m);
+        let (j, n) = check.mk_automaton(gkat, &exp);
+        match check.equiv_iter(gkat, st, j, m, &n) {
+            Ok(true) => Ok(exp),
+            Ok(false) => Err(DecompileError::RoundTrip),
+            Err(limit) => Err(DecompileError::Unknown(limit)),
+        }
+    }
+
+    fn decompile_unchecked<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        st: u64,
+        m: &Automaton<B>,
+    ) -> Result<Exp<B>, DecompileError> {
+        // bisimilar copies of a loop header become one state
+        let (st, m) = self.minimize(gkat, st, m);
+        let mut trees = HashMap::new();
+        for (i, eps) in &m.eps_hat {
+            trees.insert(*i, mk_tree(gkat, eps, m.delta_hat.get(i).unwrap()));
+        }
+        let mut states: Vec<u64> = m.eps_hat.keys().cloned().collect();
+        states.sort();
+        let mut trees = Trees {
+            fresh: states.last().map_or(0, |i| i + 1),
+            m,
+            trees,
+            folded: HashMap::new(),
+            depth: states.len(),
+        };
+        trees.structure(gkat, &states)?;
+        // states jumping to a synthesized header can close a cycle through
+        // it that structure does not see
+        let mut all: Vec<u64> = trees.trees.keys().cloned().collect();
+        all.sort();
+        if let Some(scc) = trees.cyclic(&all).pop() {
+            return Err(DecompileError::NotExpressible(scc));
+        }
+        let within = all.into_iter().collect();
+        let root = trees.inline(gkat, &mk(Node::Jump(st)), &within, &mut HashMap::new());
+        let one = gkat.mk_one();
+        Ok(to_exp(gkat, &root, &one, &mut HashMap::new()))
+    }
+}
+
+#[test]
+pub fn test() {
+    use crate::parsing::spec::ExpParser;
+    let cases = [
+        "(seq p1 p2)",
+        "(if b1 (seq p1 p3) (seq p2 p3))",
+        "(while b1 p1)",
+        "(seq (while b1 p1) (while b2 p2))",
+        "(while b1 (seq p1 (while b2 p2)))",
+        "(while b1 (if b2 p1 (while b3 p2)))",
+        "(seq p1 (while b1 (seq p2 (test 0))))",
+        "(while b2 (seq (while b1 p1) p0))",
+        "(while b1 (seq p0 (if b2 p1 p2)))",
+        "(while b1 (seq p0 (if b2 p1 p1)))",
+        "(while (not b2) (if b3 (seq p0 (while b3 (seq p1 p0))) (while b1 (if b2 p1 p2))))",
+    ];
+    for e in cases {
+        for min in [false, true] {
+            let mut gkat = PureBDDGkat::new();
+            let mut solver = Solver::new();
+            let exp = gkat.from_exp(ExpParser::new().parse(e).unwrap());
+            let (i, m) = solver.mk_automaton(&mut gkat, &exp);
+            let (i, m) = if min {
+                solver.minimize(&mut gkat, i, &m)
+            } else {
+                (i, m)
+            };
+            solver.decompile(&mut gkat, i, &m).expect(e);
+        }
+    }
+    // the loop guard is not repeated inside the body
+    let mut gkat = PureBDDGkat::new();
+    let mut solver = Solver::new();
+    let e = "(while b1 (seq p0 (if b2 p1 p2)))";
+    let exp = gkat.from_exp(ExpParser::new().parse(e).unwrap());
+    let (i, m) = solver.mk_automaton(&mut gkat, &exp);
+    let exp = solver.decompile(&mut gkat, i, &m).unwrap();
+    assert_eq!(gkat.to_exp(&exp).to_string(), e);
+    // a loop left at two points under different tests
+    let mut gkat = PureBDDGkat::new();
+    let (b1, b2) = (gkat.mk_var("b1".into()), gkat.mk_var("b2".into()));
+    let (nb1, nb2) = (gkat.mk_not(&b1), gkat.mk_not(&b2));
+    let (p1, p2) = (gkat.actions().intern("p1".into()), gkat.actions().intern("p2".into()));
+    let m = Automaton {
+        eps_hat: HashMap::from_iter([(0, nb1), (1, nb2)]),
+        delta_hat: HashMap::from_iter([(0, vec![(b1, 1, p1)]), (1, vec![(b2, 0, p2)])]),
+    };
+    let err = Solver::new().decompile(&mut gkat, 0, &m).unwrap_err();
+    assert_eq!(err, DecompileError::NotExpressible(vec![0, 1]));
+    // random automata over two tests either round-trip or are rejected as
+    // not expressible
+    let mut rng = crate::syntax::testing::Lcg::new(1);
+    let mut expressible = 0;
+    for _ in 0..200 {
+        let mut gkat = PureBDDGkat::new();
+        let (b1, b2) = (gkat.mk_var("b1".into()), gkat.mk_var("b2".into()));
+        let (nb1, nb2) = (gkat.mk_not(&b1), gkat.mk_not(&b2));
+        let atoms = [(&b1, &b2), (&b1, &nb2), (&nb1, &b2), (&nb1, &nb2)];
+        let atoms = atoms.map(|(x, y)| gkat.mk_and(x, y));
+        let acts = [gkat.actions().intern("p1".into()), gkat.actions().intern("p2".into())];
+        let size = 1 + rng.below(4);
+        let mut m = Automaton {
+            eps_hat: HashMap::new(),
+            delta_hat: HashMap::new(),
+        };
+        for st in 0..size {
+            let mut eps = gkat.mk_zero();
+            let mut delta: Vec<(_, u64, u64)> = vec![];
+            for atom in &atoms {
+                // accept, reject, or take one of the transitions
+                match rng.below(4) {
+                    0 => eps = gkat.mk_or(&eps, atom),
+                    1 => {}
+                    _ => {
+                        let (next, act) = (rng.below(size), acts[rng.below(2) as usize]);
+                        match delta.iter_mut().find(|(_, n, a)| (*n, *a) == (next, act)) {
+                            Some((b, _, _)) => *b = gkat.mk_or(b, atom),
+                            None => delta.push((atom.clone(), next, act)),
+                        }
+                    }
+                }
+            }
+            m.eps_hat.insert(st, eps);
+            m.delta_hat.insert(st, delta);
+        }
+        match Solver::new().decompile(&mut gkat, 0, &m) {
+            Ok(_) => expressible += 1,
+            Err(e) => assert!(matches!(e, DecompileError::NotExpressible(_)), "{}", e),
+        }
+    }
+    assert!(expressible > 100);
+}
@@ -8,43 +8,89 @@ impl<B: BExp> Solver<B> {
         j: u64,
         m: &Automaton<B>,
         n: &Automaton<B>,
-    ) -> bool {
-        let mut stack = vec![(i, j)];
-        while let Some((i, j)) = stack.pop() {
+    ) -> Result<bool, Limit> {
+        Ok(self.equiv_search(gkat, i, j, m, n)?.is_none())
+    }
+
+    pub fn equiv_search<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        i: u64,
+        j: u64,
+        m: &Automaton<B>,
+        n: &Automaton<B>,
+    ) -> Result<Option<Mismatch<B, u64>>, Limit> {
+        let mismatch = self.equiv_mismatch(gkat, i, j, m, n);
+        self.verdict(gkat, mismatch)
+    }
+
+    fn equiv_mismatch<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        i: u64,
+        j: u64,
+        m: &Automaton<B>,
+        n: &Automaton<B>,
+    ) -> Option<Mismatch<B, u64>> {
+        // parent pointers for reconstructing failing paths
+        let mut trace: Vec<(Option<usize>, B, u64)> = vec![];
+        let mut stack = vec![(i, j, None)];
+        while let Some((i, j, parent)) = stack.pop() {
             let mut exp1_uf = self.get_uf(i);
             let mut exp2_uf = self.get_uf(j);
 
-            if exp1_uf.equiv(&exp2_uf) {
-                continue;
-            } else if self.known_dead(&i) && self.is_dead(gkat, j, n) {
-                continue;
-            } else if self.known_dead(&j) && self.is_dead(gkat, i, m) {
+            if exp1_uf.equiv(&exp2_uf)
+                || (self.known_dead(&i) && self.dead(gkat, j, n))
+                || (self.known_dead(&j) && self.dead(gkat, i, m))
+            {
                 continue;
             } else {
+                self.stats.pairs += 1;
+                if self.out_of_budget(gkat) {
+                    return None;
+                }
                 let eps1 = m.eps_hat.get(&i).unwrap();
                 let eps2 = n.eps_hat.get(&j).unwrap();
                 let delta1 = m.delta_hat.get(&i).unwrap();
                 let delta2 = n.delta_hat.get(&j).unwrap();
 
-                if !(gkat.is_equiv(&eps1, &eps2)) {
-                    return false;
+                if !(gkat.is_equiv(eps1, eps2)) {
+                    let neps2 = gkat.mk_not(eps2);
+                    let guard = gkat.mk_and(eps1, &neps2);
+                    let left = !gkat.is_false(&guard);
+                    let guard = if left {
+                        guard
+                    } else {
+                        let neps1 = gkat.mk_not(eps1);
+                        gkat.mk_and(&neps1, eps2)
+                    };
+                    return Some(Mismatch::new(&trace, parent, guard, None, left));
                 }
                 let reject1 = self.reject(gkat, i, m);
-                let assert1 = delta2.iter().all(|(b0, st, _)| {
+                let assert1 = delta2.iter().find_map(|(b0, st, a)| {
                     let b1 = gkat.mk_and(&reject1, b0);
-                    gkat.is_false(&b1) || self.is_dead(gkat, *st, n)
+                    if gkat.is_false(&b1) || self.dead(gkat, *st, n) {
+                        None
+                    } else {
+                        Some((b1, *st, *a))
+                    }
                 });
-                if !assert1 {
-                    return false;
+                if let Some((guard, st, a)) = assert1 {
+                    return Some(Mismatch::new(&trace, parent, guard, Some((a, st)), false));
                 }
                 let reject2 = self.reject(gkat, j, n);
-                let assert2 = delta1.iter().all(|(b0, st, _)| {
+                let assert2 = delta1.iter().find_map(|(b0, st, a)| {
                     let b1 = gkat.mk_and(&reject2, b0);
-                    gkat.is_false(&b1) || self.is_dead(gkat, *st, m)
+                    if gkat.is_false(&b1) || self.dead(gkat, *st, m) {
+                        None
+                    } else {
+                        Some((b1, *st, *a))
+                    }
                 });
-                if !assert2 {
-                    return false;
+                if let Some((guard, st, a)) = assert2 {
+                    return Some(Mismatch::new(&trace, parent, guard, Some((a, st)), true));
                 }
+                self.relate(i, j);
                 for (be1, st1, p) in delta1 {
                     for (be2, st2, q) in delta2 {
                         let b1b2 = gkat.mk_and(be1, be2);
@@ -52,18 +98,24 @@ impl<B: BExp> Solver<B> {
                             continue;
                         } else if p == q {
                             exp1_uf.union(&mut exp2_uf);
-                            stack.push((*st1, *st2));
+                            self.stats.unions += 1;
+                            trace.push((parent, b1b2, *p));
+                            stack.push((*st1, *st2, Some(trace.len() - 1)));
                         } else {
-                            let result1 = self.is_dead(gkat, *st1, m);
-                            let result2 = self.is_dead(gkat, *st2, n);
-                            if !(result1 && result2) {
-                                return false;
+                            let result1 = self.dead(gkat, *st1, m);
+                            let result2 = self.dead(gkat, *st2, n);
+                            if !result1 {
+                                let suffix = Some((*p, *st1));
+                                return Some(Mismatch::new(&trace, parent, b1b2, suffix, true));
+                            } else if !result2 {
+                                let suffix = Some((*q, *st2));
+                                return Some(Mismatch::new(&trace, parent, b1b2, suffix, false));
                             }
                         }
                     }
                 }
             }
         }
-        return true;
+        None
     }
 }
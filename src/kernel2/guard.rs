@@ -10,9 +10,9 @@ pub struct GuardIterator<'a, B, G> {
 impl<'a, B: BExp, G: Gkat<B>> GuardIterator<'a, B, G> {
     pub fn new(gkat: &'a mut G, guard: &'a B, iter: Iter<'a, (B, u64, u64)>) -> Self {
         Self {
-            gkat: gkat,
-            guard: guard,
-            iter: iter,
+            gkat,
+            guard,
+            iter,
         }
     }
 }
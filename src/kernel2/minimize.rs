@@ -0,0 +1,173 @@
+use super::*;
+use crate::hash::{HashMap, HashMapExt, HashSet, HashSetExt};
+
+// Eps guard and merged transition guards keyed by (target block, action).
+type Signature<B> = (B, Vec<((usize, u64), B)>);
+
+// Old block and the (target block, action) keys of a signature.
+type Shape = (usize, Vec<(usize, u64)>);
+
+impl<B: BExp> Automaton<B> {
+    // Number of states and transitions.
+    pub fn size(&self) -> (usize, usize) {
+        let trans = self.delta_hat.values().map(|d| d.len()).sum();
+        (self.eps_hat.len(), trans)
+    }
+}
+
+impl<B: BExp> Solver<B> {
+    // Merge bisimilar states of the automaton starting from st and drop
+    // states that are dead or unreachable. States are renumbered.
+    pub fn minimize<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        st: u64,
+        m: &Automaton<B>,
+    ) -> (u64, Automaton<B>) {
+        // live states reachable from st
+        let mut states = vec![];
+        let mut seen = HashSet::new();
+        let mut stack = vec![st];
+        while let Some(i) = stack.pop() {
+            if !seen.insert(i) || self.dead(gkat, i, m) {
+                continue;
+            }
+            states.push(i);
+            for (_, next, _) in m.delta_hat.get(&i).unwrap() {
+                stack.push(*next);
+            }
+        }
+        if states.is_empty() {
+            let st = self.new_state();
+            let mut eps_hat = HashMap::new();
+            eps_hat.insert(st, gkat.mk_zero());
+            let mut delta_hat = HashMap::new();
+            delta_hat.insert(st, vec![]);
+            let automaton = Automaton { eps_hat, delta_hat };
+            return (st, automaton);
+        }
+        // transitions into dead states behave like rejection
+        let mut delta = HashMap::new();
+        for i in &states {
+            let mut elems = vec![];
+            for (b, next, a) in m.delta_hat.get(i).unwrap() {
+                if !self.dead(gkat, *next, m) {
+                    elems.push((b.clone(), *next, *a));
+                }
+            }
+            delta.insert(*i, elems);
+        }
+
+        // refine until no block splits
+        let mut block: HashMap<u64, usize> = states.iter().map(|i| (*i, 0)).collect();
+        let mut count = 1;
+        let mut reps: Vec<(usize, Signature<B>)>;
+        loop {
+            reps = vec![];
+            let mut next_block = HashMap::new();
+            // blocks by old block and signature; equal guards need not be
+            // identical, so a miss falls back to the blocks of the same
+            // old block with the same targets and actions
+            let mut exact: HashMap<(usize, Signature<B>), usize> = HashMap::new();
+            let mut shapes: HashMap<Shape, Vec<usize>> = HashMap::new();
+            for i in &states {
+                let eps = m.eps_hat.get(i).unwrap();
+                let sig = signature(gkat, eps, delta.get(i).unwrap(), &block);
+                let old = *block.get(i).unwrap();
+                if let Some(k) = exact.get(&(old, sig.clone())) {
+                    next_block.insert(*i, *k);
+                    continue;
+                }
+                let shape = (old, sig.1.iter().map(|(k, _)| *k).collect());
+                let candidates = shapes.entry(shape).or_default();
+                let found = candidates
+                    .iter()
+                    .find(|k| same_signature(gkat, &sig, &reps[**k].1));
+                let k = match found {
+                    Some(k) => *k,
+                    None => {
+                        reps.push((old, sig.clone()));
+                        candidates.push(reps.len() - 1);
+                        reps.len() - 1
+                    }
+                };
+                exact.insert((old, sig), k);
+                next_block.insert(*i, k);
+            }
+            block = next_block;
+            if reps.len() == count {
+                break;
+            }
+            count = reps.len();
+        }
+
+        let ids: Vec<u64> = reps.iter().map(|_| self.new_state()).collect();
+        let mut eps_hat = HashMap::new();
+        let mut delta_hat = HashMap::new();
+        for (k, (_, (eps, trans))) in reps.into_iter().enumerate() {
+            let elems = trans.into_iter().map(|((blk, a), b)| (b, ids[blk], a));
+            eps_hat.insert(ids[k], eps);
+            delta_hat.insert(ids[k], elems.collect());
+        }
+        let automaton = Automaton { eps_hat, delta_hat };
+        (ids[*block.get(&st).unwrap()], automaton)
+    }
+}
+
+fn signature<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    eps: &B,
+    delta: &[(B, u64, u64)],
+    block: &HashMap<u64, usize>,
+) -> Signature<B> {
+    let mut trans: Vec<((usize, u64), B)> = vec![];
+    for (b, next, a) in delta {
+        let key = (*block.get(next).unwrap(), *a);
+        match trans.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, guard)) => *guard = gkat.mk_or(guard, b),
+            None => trans.push((key, b.clone())),
+        }
+    }
+    trans.sort_by_key(|(k, _)| *k);
+    (eps.clone(), trans)
+}
+
+fn same_signature<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    sig1: &Signature<B>,
+    sig2: &Signature<B>,
+) -> bool {
+    let (eps1, trans1) = sig1;
+    let (eps2, trans2) = sig2;
+    if trans1.len() != trans2.len() || !gkat.is_equiv(eps1, eps2) {
+        return false;
+    }
+    trans1
+        .iter()
+        .zip(trans2.iter())
+        .all(|((k1, b1), (k2, b2))| k1 == k2 && gkat.is_equiv(b1, b2))
+}
+
+#[test]
+pub fn test() {
+    use crate::parsing::spec::ExpParser;
+    let cases = [
+        "(seq (if b1 p1 p1) p2)",
+        "(while b1 (if b2 (seq p1 p2) (seq p1 p2)))",
+        "(seq (while b1 p1) (while b1 p1))",
+        "(seq p1 (test 0))",
+        "(if b1 (seq p1 (test 0)) p2)",
+    ];
+    for e in cases {
+        let mut gkat = PureBDDGkat::new();
+        let mut solver = Solver::new();
+        let exp = gkat.from_exp(ExpParser::new().parse(e).unwrap());
+        let (i, m) = solver.mk_automaton(&mut gkat, &exp);
+        let (j, n) = solver.minimize(&mut gkat, i, &m);
+        assert!(n.size().0 <= m.size().0);
+        assert_eq!(solver.equiv_iter(&mut gkat, i, j, &m, &n), Ok(true));
+        // minimizing again changes nothing
+        let (_, o) = solver.minimize(&mut gkat, j, &n);
+        assert_eq!(o.size(), n.size());
+    }
+}
@@ -1,9 +1,16 @@
 mod automaton;
+mod certificate;
+mod decompile;
 mod equiv_iter;
 mod guard;
+mod incl_iter;
+mod minimize;
 mod solver;
+mod triple;
+mod witness;
 
 use crate::syntax::*;
 pub use automaton::*;
+pub use decompile::DecompileError;
 use guard::*;
 pub use solver::*;
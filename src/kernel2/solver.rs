@@ -1,8 +1,9 @@
 use std::marker::PhantomData;
 
 use super::*;
+use crate::hash::{HashMap, HashSet};
 use disjoint_sets::UnionFindNode;
-use gxhash::{HashMap, HashSet};
+use std::time::Instant;
 
 pub struct Solver<B> {
     // automaton states
@@ -11,10 +12,26 @@ pub struct Solver<B> {
     dead_states: HashSet<u64>,
     explored: HashSet<u64>,
     uf_table: HashMap<u64, UnionFindNode<()>>,
+    // pairs expanded by equiv_iter, recorded for certificates
+    related: Option<Vec<(u64, u64)>>,
+    // postconditions of actions
+    hyps: Hypotheses<B>,
+    // resource limits
+    deadline: Option<Instant>,
+    max_pairs: Option<usize>,
+    stopped: Option<Limit>,
+    // statistics
+    pub stats: SolverStats,
     // phantom
     phantom: PhantomData<B>,
 }
 
+impl<B: BExp> Default for Solver<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<B: BExp> Solver<B> {
     pub fn new() -> Self {
         Self {
@@ -24,17 +41,89 @@ impl<B: BExp> Solver<B> {
             dead_states: HashSet::default(),
             explored: HashSet::default(),
             uf_table: HashMap::default(),
+            related: None,
+            hyps: Hypotheses::new(),
+            deadline: None,
+            max_pairs: None,
+            stopped: None,
+            stats: SolverStats::default(),
             // phantom
             phantom: PhantomData,
         }
     }
 
+    // Build automata modulo Hoare hypotheses on actions.
+    pub fn set_hypotheses(&mut self, hyps: Hypotheses<B>) {
+        self.hyps = hyps;
+    }
+
+    // Fresh solver with the same hypotheses, numbering its states past
+    // those of m and of the automata built so far.
+    pub fn fork(&self, m: &Automaton<B>) -> Self {
+        let mut solver = Self::new();
+        solver.hyps = self.hyps.clone();
+        let last = m.eps_hat.keys().max().map_or(0, |st| st + 1);
+        solver.state_stamp = self.state_stamp.max(last);
+        solver
+    }
+
     pub fn new_state(&mut self) -> u64 {
         let st = self.state_stamp;
         self.state_stamp = st + 1;
         st
     }
 
+    // Record the pairs equiv_iter relates, which certificate exports.
+    pub fn record_relation(&mut self) {
+        self.related = Some(vec![]);
+    }
+
+    #[inline]
+    pub fn relate(&mut self, i: u64, j: u64) {
+        if let Some(related) = &mut self.related {
+            related.push((i, j));
+        }
+    }
+
+    // None unless record_relation was called.
+    pub fn relation(&self) -> Option<&[(u64, u64)]> {
+        self.related.as_deref()
+    }
+
+    // Bound the search; the gkat enforces its own limits.
+    pub fn set_limits(&mut self, limits: &Limits) {
+        self.deadline = limits.deadline();
+        self.max_pairs = limits.max_pairs;
+        self.stopped = None;
+    }
+
+    // Limit that stopped the search or the gkat, making results meaningless.
+    pub fn limit_hit<G: Gkat<B>>(&self, gkat: &G) -> Option<Limit> {
+        self.stopped.or(gkat.exhausted())
+    }
+
+    // Result of a search, unless a limit cut it short.
+    pub fn verdict<G: Gkat<B>, T>(&self, gkat: &G, result: T) -> Result<T, Limit> {
+        match self.limit_hit(gkat) {
+            Some(limit) => Err(limit),
+            None => Ok(result),
+        }
+    }
+
+    pub fn out_of_budget<G: Gkat<B>>(&mut self, gkat: &G) -> bool {
+        if self.max_pairs.is_some_and(|n| self.stats.pairs > n) {
+            self.stopped.get_or_insert(Limit::Pairs);
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.stopped.get_or_insert(Limit::Timeout);
+        }
+        self.limit_hit(gkat).is_some()
+    }
+
+    #[inline]
+    pub fn get_post(&self, a: u64) -> Option<&Vec<(B, B)>> {
+        self.hyps.get(a)
+    }
+
     pub fn get_uf(&mut self, st: u64) -> UnionFindNode<()> {
         match self.uf_table.get(&st) {
             Some(node) => node.clone(),
@@ -60,17 +149,31 @@ impl<B: BExp> Solver<B> {
         self.dead_states.contains(st)
     }
 
-    pub fn is_dead<G: Gkat<B>>(&mut self, gkat: &mut G, st: u64, m: &Automaton<B>) -> bool {
+    // Whether state st of m accepts no guarded string.
+    pub fn is_dead<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        st: u64,
+        m: &Automaton<B>,
+    ) -> Result<bool, Limit> {
+        let dead = self.dead(gkat, st, m);
+        self.verdict(gkat, dead)
+    }
+
+    // is_dead for searches, which check the limits themselves.
+    pub(super) fn dead<G: Gkat<B>>(&mut self, gkat: &mut G, st: u64, m: &Automaton<B>) -> bool {
         let mut stack = Vec::new();
         stack.push(st);
         self.explored.clear();
+        self.stats.is_dead_calls += 1;
         while let Some(st) = stack.pop() {
             if self.known_dead(&st) || self.explored.contains(&st) {
                 continue;
             }
             self.explored.insert(st);
+            self.stats.dead_explored += 1;
             let eps = m.eps_hat.get(&st).unwrap();
-            if gkat.is_false(&eps) {
+            if gkat.is_false(eps) {
                 for (_, st, _) in m.delta_hat.get(&st).unwrap() {
                     stack.push(*st);
                 }
@@ -78,7 +181,10 @@ impl<B: BExp> Solver<B> {
                 return false;
             }
         }
-        self.dead_states.extend(self.explored.iter());
-        return true;
+        // states explored under an exhausted gkat may not be dead
+        if self.limit_hit(gkat).is_none() {
+            self.dead_states.extend(self.explored.iter());
+        }
+        true
     }
 }
@@ -0,0 +1,84 @@
+use super::*;
+
+impl<B: BExp> Solver<B> {
+    // Automaton for the runs of exp from pre that end outside post.
+    fn mk_triple<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        pre: &B,
+        exp: &Exp<B>,
+        post: &B,
+    ) -> (u64, Automaton<B>) {
+        let npost = gkat.mk_not(post);
+        let p0 = gkat.mk_test(pre.clone());
+        let p1 = gkat.mk_test(npost);
+        let p2 = gkat.mk_seq(exp.clone(), p1);
+        let exp = gkat.mk_seq(p0, p2);
+        self.mk_automaton(gkat, &exp)
+    }
+
+    // Check the partial correctness triple {pre} exp {post}.
+    pub fn triple_iter<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        pre: &B,
+        exp: &Exp<B>,
+        post: &B,
+    ) -> Result<bool, Limit> {
+        let (st, m) = self.mk_triple(gkat, pre, exp, post);
+        self.is_dead(gkat, st, &m)
+    }
+
+    // Run of exp from pre ending outside post, if the triple fails.
+    pub fn triple_witness<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        pre: &B,
+        exp: &Exp<B>,
+        post: &B,
+    ) -> Option<GuardedString> {
+        // only called once triple_iter failed, so run without limits
+        gkat.set_limits(&Limits::default());
+        let (st, m) = self.mk_triple(gkat, pre, exp, post);
+        let (steps, last) = m.live_path(gkat, st)?;
+        let s = mk_guarded_string(gkat, &steps, &last);
+        assert!(m.accepts(gkat, st, &s));
+        Some(s)
+    }
+}
+
+#[test]
+pub fn test() {
+    use crate::parsing::spec::{BExpParser, ExpParser};
+    let cases = [
+        ("b1", "(while b1 p1)", "(not b1)", true),
+        ("1", "(if b1 (seq p1 (test b2)) (test b2))", "b2", true),
+        ("b1", "(seq p1 (test b2))", "(and b1 b2)", false),
+        ("1", "(while b1 (seq p1 (test b2)))", "b2", false),
+        ("b2", "(while b1 (seq p1 (test b2)))", "b2", true),
+    ];
+    for (pre, e, post, expected) in cases {
+        let mut gkat = PureBDDGkat::new();
+        let mut solver = Solver::new();
+        let pre = gkat.from_bexp(BExpParser::new().parse(pre).unwrap());
+        let exp = gkat.from_exp(ExpParser::new().parse(e).unwrap());
+        let post = gkat.from_bexp(BExpParser::new().parse(post).unwrap());
+        assert_eq!(solver.triple_iter(&mut gkat, &pre, &exp, &post), Ok(expected));
+        let w = solver.triple_witness(&mut gkat, &pre, &exp, &post);
+        assert_eq!(w.is_none(), expected);
+    }
+    // a triple cut short by a limit has no verdict
+    let mut gkat = PureBDDGkat::new();
+    let mut solver = Solver::new();
+    let pre = gkat.from_bexp(BExpParser::new().parse("b2").unwrap());
+    let exp = ExpParser::new().parse("(while b1 (seq p1 (test (and b2 b3))))");
+    let exp = gkat.from_exp(exp.unwrap());
+    gkat.set_limits(&Limits {
+        max_nodes: Some(1),
+        ..Limits::default()
+    });
+    assert_eq!(
+        solver.triple_iter(&mut gkat, &pre, &exp, &pre),
+        Err(Limit::Nodes)
+    );
+}
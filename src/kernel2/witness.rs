@@ -0,0 +1,117 @@
+use super::*;
+use crate::hash::{HashMap, HashMapExt};
+use std::collections::VecDeque;
+
+impl<B: BExp> Automaton<B> {
+    // Shortest symbolic path from st to acceptance, if st is not dead.
+    pub fn live_path<G: Gkat<B>>(&self, gkat: &mut G, st: u64) -> Option<(Vec<(B, u64)>, B)> {
+        let mut parents: HashMap<u64, Option<(u64, B, u64)>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        parents.insert(st, None);
+        queue.push_back(st);
+        while let Some(st) = queue.pop_front() {
+            let eps = self.eps_hat.get(&st).unwrap();
+            if !gkat.is_false(eps) {
+                let mut steps = vec![];
+                let mut cur = st;
+                while let Some(Some((prev, b, a))) = parents.get(&cur) {
+                    steps.push((b.clone(), *a));
+                    cur = *prev;
+                }
+                steps.reverse();
+                return Some((steps, eps.clone()));
+            }
+            for (b, next, a) in self.delta_hat.get(&st).unwrap() {
+                if !parents.contains_key(next) {
+                    parents.insert(*next, Some((st, b.clone(), *a)));
+                    queue.push_back(*next);
+                }
+            }
+        }
+        None
+    }
+
+    // Run a guarded string through the automaton starting from st.
+    pub fn accepts<G: Gkat<B>>(&self, gkat: &mut G, st: u64, s: &GuardedString) -> bool {
+        let mut st = st;
+        for (atom, act) in s.atoms.iter().zip(s.acts.iter()) {
+            let at = gkat.mk_atom(atom);
+            let next = self.delta_hat.get(&st).unwrap().iter().find(|(b, _, _)| {
+                let ab = gkat.mk_and(&at, b);
+                !gkat.is_false(&ab)
+            });
+            match next {
+                Some((_, next, p)) if gkat.act_name(*p) == *act => st = *next,
+                _ => return false,
+            }
+        }
+        let at = gkat.mk_atom(s.atoms.last().unwrap());
+        let eps = self.eps_hat.get(&st).unwrap();
+        let b = gkat.mk_and(&at, eps);
+        !gkat.is_false(&b)
+    }
+}
+
+impl<B: BExp> Solver<B> {
+    pub fn equiv_witness<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        i: u64,
+        j: u64,
+        m: &Automaton<B>,
+        n: &Automaton<B>,
+    ) -> Result<Option<Witness>, Limit> {
+        let Some(mismatch) = self.equiv_search(gkat, i, j, m, n)? else {
+            return Ok(None);
+        };
+        // the verdict is in, so build the witness without limits
+        gkat.set_limits(&Limits::default());
+        let w = self.mk_witness(gkat, mismatch, m, n);
+        assert!(m.accepts(gkat, i, &w.string) == w.left_accepts);
+        assert!(n.accepts(gkat, j, &w.string) != w.left_accepts);
+        Ok(Some(w))
+    }
+
+    pub fn mk_witness<G: Gkat<B>>(
+        &mut self,
+        gkat: &mut G,
+        mismatch: Mismatch<B, u64>,
+        m: &Automaton<B>,
+        n: &Automaton<B>,
+    ) -> Witness {
+        let accepting = if mismatch.left_accepts { m } else { n };
+        let mut steps = mismatch.path;
+        let last = match mismatch.suffix {
+            None => mismatch.guard,
+            Some((a, st)) => {
+                steps.push((mismatch.guard, a));
+                let (live_steps, eps) = accepting.live_path(gkat, st).unwrap();
+                steps.extend(live_steps);
+                eps
+            }
+        };
+        Witness {
+            string: mk_guarded_string(gkat, &steps, &last),
+            left_accepts: mismatch.left_accepts,
+        }
+    }
+}
+
+#[test]
+pub fn test() {
+    use crate::kernel1;
+    use crate::parsing::spec::ExpParser;
+    for (e1, e2) in WITNESS_CASES {
+        let mut gkat = PureBDDGkat::new();
+        let mut solver = Solver::new();
+        let exp1 = gkat.from_exp(ExpParser::new().parse(e1).unwrap());
+        let exp2 = gkat.from_exp(ExpParser::new().parse(e2).unwrap());
+        let (i, m) = solver.mk_automaton(&mut gkat, &exp1);
+        let (j, n) = solver.mk_automaton(&mut gkat, &exp2);
+        let w = solver.equiv_witness(&mut gkat, i, j, &m, &n).unwrap().unwrap();
+        // cross-check against the derivative kernel
+        let mut k1 = kernel1::Solver::new();
+        assert_eq!(k1.accepts(&mut gkat, &exp1, &w.string), w.left_accepts);
+        assert_eq!(k1.accepts(&mut gkat, &exp2, &w.string), !w.left_accepts);
+    }
+}
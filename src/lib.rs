@@ -0,0 +1,393 @@
+pub mod certificate;
+mod hash;
+pub mod kernel1;
+pub mod kernel2;
+pub mod parsing;
+pub mod syntax;
+
+use clap::ValueEnum;
+use parsing::parse_exp;
+use std::fmt;
+use syntax::*;
+
+// Types reachable from the options and results of check_equivalence.
+pub use parsing::ParseError;
+pub use syntax::{
+    Atom, GkatStats, GuardedString, Limit, Limits, Reorder, SolverStats, Stats, VarOrder, Witness,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Kernel {
+    K1, // Symbolic derivative method
+    K2, // Symbolic Thompson's construction
+}
+
+// Backends compiled in; the default picks one per input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Solver {
+    // explicit up to Options::explicit_vars test variables, else FALLBACK
+    #[default]
+    Auto,
+    #[cfg(feature = "cudd")]
+    BDD, // CUDD
+    #[cfg(feature = "sat")]
+    SAT, // MiniSat2 via LogicNG
+    #[cfg(feature = "sat")]
+    AIG, // and-inverter graph with SAT sweeping
+    #[cfg(feature = "sat")]
+    Hybrid, // pure-Rust BDD, SAT for guards over a node budget
+    PureBDD, // pure-Rust BDD
+    Explicit, // truth tables over the atoms, for few test variables
+}
+
+impl Solver {
+    // Backend of Auto for inputs with too many variables to be explicit.
+    #[cfg(feature = "cudd")]
+    pub const FALLBACK: Solver = Solver::BDD;
+    #[cfg(not(feature = "cudd"))]
+    pub const FALLBACK: Solver = Solver::PureBDD;
+
+    // The solver itself, or FALLBACK for Auto once explicit is ruled out.
+    pub fn backend(self) -> Solver {
+        match self {
+            Solver::Auto => Solver::FALLBACK,
+            s => s,
+        }
+    }
+
+    // Whether the backend follows Options::reorder; only CUDD reorders.
+    pub fn reorders(self) -> bool {
+        #[cfg(feature = "cudd")]
+        if self.backend() == Solver::BDD {
+            return true;
+        }
+        false
+    }
+
+    // Whether the backend follows Options::var_order; SAT and truth
+    // tables have no order.
+    pub fn orders_vars(self) -> bool {
+        #[cfg(feature = "sat")]
+        if matches!(self, Solver::SAT | Solver::AIG) {
+            return false;
+        }
+        self != Solver::Explicit
+    }
+}
+
+// Settings for check_equivalence.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub kernel: Kernel,
+    pub solver: Solver,
+    // minimize k2 automata before comparing them
+    pub minimize: bool,
+    // compute a distinguishing guarded string on failure
+    pub witness: bool,
+    pub limits: Limits,
+    // CUDD dynamic reordering and the initial variable order
+    pub reorder: Reorder,
+    pub var_order: VarOrder,
+    // Solver::Auto uses ExplicitGkat up to this many test variables
+    pub explicit_vars: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            kernel: Kernel::K1,
+            solver: Solver::default(),
+            minimize: false,
+            witness: false,
+            limits: Limits::default(),
+            reorder: Reorder::None,
+            var_order: VarOrder::FirstSeen,
+            explicit_vars: 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub equivalent: bool,
+    // backend that ran, never Auto
+    pub solver: Solver,
+    // set when requested and the expressions differ
+    pub witness: Option<Witness>,
+    pub stats: Stats,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Parse(ParseError),
+    // an option the solver cannot honor
+    Unsupported(&'static str),
+    // a resource limit stopped the check before it reached a verdict
+    Unknown(Limit),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "parse error: {}", e),
+            Error::Unsupported(e) => write!(f, "unsupported option: {}", e),
+            Error::Unknown(limit) => write!(f, "unknown: {}", limit),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Decide whether two expressions in s-expression syntax are equivalent.
+pub fn check_equivalence(exp1: &str, exp2: &str, options: &Options) -> Result<Outcome, Error> {
+    if options.reorder != Reorder::None && !options.solver.reorders() {
+        return Err(Error::Unsupported("reorder needs the bdd solver"));
+    }
+    if options.var_order != VarOrder::FirstSeen && !options.solver.orders_vars() {
+        return Err(Error::Unsupported("var_order needs a BDD solver"));
+    }
+    let exp1 = parse_exp(exp1).map_err(Error::Parse)?;
+    let exp2 = parse_exp(exp2).map_err(Error::Parse)?;
+    let outcome = |outcome: Outcome, solver| Outcome { solver, ..outcome };
+    if let Some(n) = explicit_size(&exp1, &exp2, options)? {
+        let mut gkat = ExplicitGkat::new(n);
+        let result = match options.kernel {
+            Kernel::K1 => equiv_k1(&mut gkat, exp1, exp2, options),
+            Kernel::K2 => equiv_k2(&mut gkat, exp1, exp2, options),
+        };
+        return result.map(|o| outcome(o, Solver::Explicit));
+    }
+    let backend = options.solver.backend();
+    let result = match (options.kernel, backend) {
+        #[cfg(feature = "cudd")]
+        (Kernel::K1, Solver::BDD) => equiv_k1(&mut BDDGkat::new(), exp1, exp2, options),
+        #[cfg(feature = "sat")]
+        (Kernel::K1, Solver::SAT) => equiv_k1(&mut SATGkat::new(), exp1, exp2, options),
+        #[cfg(feature = "sat")]
+        (Kernel::K1, Solver::AIG) => equiv_k1(&mut AIGGkat::new(), exp1, exp2, options),
+        #[cfg(feature = "sat")]
+        (Kernel::K1, Solver::Hybrid) => equiv_k1(&mut HybridGkat::new(), exp1, exp2, options),
+        (Kernel::K1, Solver::PureBDD) => equiv_k1(&mut PureBDDGkat::new(), exp1, exp2, options),
+        #[cfg(feature = "cudd")]
+        (Kernel::K2, Solver::BDD) => equiv_k2(&mut BDDGkat::new(), exp1, exp2, options),
+        #[cfg(feature = "sat")]
+        (Kernel::K2, Solver::SAT) => equiv_k2(&mut SATGkat::new(), exp1, exp2, options),
+        #[cfg(feature = "sat")]
+        (Kernel::K2, Solver::AIG) => equiv_k2(&mut AIGGkat::new(), exp1, exp2, options),
+        #[cfg(feature = "sat")]
+        (Kernel::K2, Solver::Hybrid) => equiv_k2(&mut HybridGkat::new(), exp1, exp2, options),
+        (Kernel::K2, Solver::PureBDD) => equiv_k2(&mut PureBDDGkat::new(), exp1, exp2, options),
+        // explicit_size took these
+        (_, Solver::Auto | Solver::Explicit) => unreachable!(),
+    };
+    result.map(|o| outcome(o, backend))
+}
+
+// Variables of ExplicitGkat, when the solver takes it for the expressions.
+fn explicit_size(
+    exp1: &parsing::Exp,
+    exp2: &parsing::Exp,
+    options: &Options,
+) -> Result<Option<usize>, Error> {
+    let max = match options.solver {
+        Solver::Auto => options.explicit_vars,
+        Solver::Explicit => ExplicitGkat::MAX_VARS,
+        _ => return Ok(None),
+    };
+    let mut guards = vec![];
+    exp_guards(exp1, &mut guards);
+    exp_guards(exp2, &mut guards);
+    let extra = options.var_order.vars(&guards);
+    match ExplicitGkat::size_for(&guards, extra, max) {
+        None if options.solver == Solver::Explicit => {
+            Err(Error::Unsupported("too many test variables for the explicit solver"))
+        }
+        size => Ok(size),
+    }
+}
+
+// Apply the limits and ordering options before any expression is built.
+fn setup<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    exp1: &parsing::Exp,
+    exp2: &parsing::Exp,
+    options: &Options,
+) {
+    gkat.set_limits(&options.limits);
+    gkat.set_reordering(options.reorder);
+    let mut guards = vec![];
+    exp_guards(exp1, &mut guards);
+    exp_guards(exp2, &mut guards);
+    for x in options.var_order.vars(&guards) {
+        gkat.mk_var(x);
+    }
+}
+
+fn equiv_k1<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    exp1: parsing::Exp,
+    exp2: parsing::Exp,
+    options: &Options,
+) -> Result<Outcome, Error> {
+    let mut solver = kernel1::Solver::new();
+    solver.set_limits(&options.limits);
+    setup(gkat, &exp1, &exp2, options);
+    let exp1 = gkat.from_exp(exp1);
+    let exp2 = gkat.from_exp(exp2);
+    let outcome = if options.witness {
+        let w = solver.equiv_witness(gkat, &exp1, &exp2).map_err(Error::Unknown)?;
+        Outcome {
+            equivalent: w.is_none(),
+            witness: w,
+            solver: options.solver,
+            stats: Stats::default(),
+        }
+    } else {
+        Outcome {
+            equivalent: solver.equiv_iter(gkat, &exp1, &exp2).map_err(Error::Unknown)?,
+            witness: None,
+            solver: options.solver,
+            stats: Stats::default(),
+        }
+    };
+    Ok(Outcome {
+        stats: Stats {
+            solver: solver.stats.clone(),
+            gkat: gkat.stats(),
+        },
+        ..outcome
+    })
+}
+
+fn equiv_k2<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    exp1: parsing::Exp,
+    exp2: parsing::Exp,
+    options: &Options,
+) -> Result<Outcome, Error> {
+    let mut solver = kernel2::Solver::new();
+    solver.set_limits(&options.limits);
+    setup(gkat, &exp1, &exp2, options);
+    let exp1 = gkat.from_exp(exp1);
+    let exp2 = gkat.from_exp(exp2);
+    let (mut i, mut m) = solver.mk_automaton(gkat, &exp1);
+    let (mut j, mut n) = solver.mk_automaton(gkat, &exp2);
+    if let Some(limit) = solver.limit_hit(gkat) {
+        return Err(Error::Unknown(limit));
+    }
+    if options.minimize {
+        (i, m) = solver.minimize(gkat, i, &m);
+        (j, n) = solver.minimize(gkat, j, &n);
+    }
+    let outcome = if options.witness {
+        let w = solver.equiv_witness(gkat, i, j, &m, &n).map_err(Error::Unknown)?;
+        Outcome {
+            equivalent: w.is_none(),
+            witness: w,
+            solver: options.solver,
+            stats: Stats::default(),
+        }
+    } else {
+        Outcome {
+            equivalent: solver.equiv_iter(gkat, i, j, &m, &n).map_err(Error::Unknown)?,
+            witness: None,
+            solver: options.solver,
+            stats: Stats::default(),
+        }
+    };
+    Ok(Outcome {
+        stats: Stats {
+            solver: solver.stats.clone(),
+            gkat: gkat.stats(),
+        },
+        ..outcome
+    })
+}
+
+#[test]
+pub fn test() {
+    // CUDD is left out, the rest of the suite does not use it either
+    #[allow(unused_mut)]
+    let mut solvers = vec![Solver::Auto, Solver::Explicit, Solver::PureBDD];
+    #[cfg(feature = "sat")]
+    solvers.extend([Solver::SAT, Solver::AIG, Solver::Hybrid]);
+    let kernels = [Kernel::K1, Kernel::K2];
+    for (solver, kernel) in solvers.into_iter().flat_map(|s| kernels.map(|k| (s, k))) {
+        let options = Options {
+            kernel,
+            solver,
+            witness: true,
+            ..Options::default()
+        };
+        let outcome = check_equivalence(
+            "(while b1 p1)",
+            "(while b1 (seq p1 (while b1 p1)))",
+            &options,
+        );
+        let outcome = outcome.unwrap();
+        assert!(outcome.equivalent);
+        // only Auto picks a backend, explicit for this few variables
+        match solver {
+            Solver::Auto => assert_eq!(outcome.solver, Solver::Explicit),
+            _ => assert_eq!(outcome.solver, solver),
+        }
+        let outcome = check_equivalence("(seq p1 p2)", "(seq p1 p3)", &options).unwrap();
+        assert!(!outcome.equivalent && outcome.witness.is_some());
+        assert!(outcome.stats.solver.pairs > 0);
+        let w = outcome.witness.unwrap().string;
+        assert_eq!(w.acts, ["p1", "p2"].map(String::from));
+        let ordered = Options {
+            var_order: VarOrder::Static,
+            ..options.clone()
+        };
+        let outcome = check_equivalence(
+            "(if (and b1 b2) p1 p2)",
+            "(if b2 (if b1 p1 p2) p2)",
+            &ordered,
+        );
+        match solver.orders_vars() {
+            true => assert!(outcome.unwrap().equivalent),
+            false => assert!(matches!(outcome, Err(Error::Unsupported(_)))),
+        }
+        let reordered = Options {
+            reorder: Reorder::Sift,
+            ..options.clone()
+        };
+        let outcome = check_equivalence("p1", "p1", &reordered);
+        match solver.reorders() {
+            true => assert!(outcome.unwrap().equivalent),
+            false => assert!(matches!(outcome, Err(Error::Unsupported(_)))),
+        }
+        let outcome = check_equivalence("(seq p1", "p1", &options);
+        assert!(matches!(outcome, Err(Error::Parse(_))));
+        let limited = Options {
+            limits: Limits {
+                max_pairs: Some(1),
+                ..Limits::default()
+            },
+            ..options.clone()
+        };
+        let outcome = check_equivalence(
+            "(while b1 (seq p1 p2))",
+            "(seq (while b1 (seq p1 p2)) (while b1 p3))",
+            &limited,
+        );
+        assert!(matches!(outcome, Err(Error::Unknown(Limit::Pairs))));
+    }
+    // Auto leaves inputs over explicit_vars to FALLBACK, Explicit refuses
+    // them beyond what its tables hold
+    let options = Options {
+        explicit_vars: 0,
+        ..Options::default()
+    };
+    let outcome = check_equivalence("(test b1)", "(test b1)", &options).unwrap();
+    assert_eq!(outcome.solver, Solver::FALLBACK);
+    let tests = (0..=ExplicitGkat::MAX_VARS).map(|i| format!(" x{}", i));
+    let exp = format!("(test (and{}))", tests.collect::<String>());
+    let options = Options {
+        solver: Solver::Explicit,
+        ..Options::default()
+    };
+    let outcome = check_equivalence(&exp, &exp, &options);
+    assert!(matches!(outcome, Err(Error::Unsupported(_))));
+}
@@ -1,82 +1,514 @@
-mod kernel1;
-mod kernel2;
-mod parsing;
-mod syntax;
+mod batch;
+mod output;
 
-use clap::{Parser, ValueEnum};
+use clap::{CommandFactory, Parser, Subcommand, error::ErrorKind};
 use mimalloc::MiMalloc;
-use parsing::*;
-use std::fs;
-use syntax::*;
+use output::{Format, Status, print_header, print_record, value_name};
+use parsing::{Goal, parse, parse_certificate};
+use rust_gkat::syntax::*;
+use rust_gkat::{Kernel, Solver, certificate, kernel1, kernel2, parsing};
+use std::{
+    fmt, fs,
+    time::{Duration, Instant},
+};
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum Kernel {
-    K1, // Symbolic derivative method
-    K2, // Symbolic Thompson's construction
-}
-
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum Solver {
-    BDD, // CUDD
-    SAT, // MiniSat2 via LogicNG
-}
-
 #[derive(Parser, Debug)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
-    #[arg(short, long, value_enum, default_value_t = Kernel::K1)]
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(short, long, value_enum, default_value_t = Kernel::K1, global = true)]
     kernel: Kernel,
-    #[arg(short, long, value_enum, default_value_t = Solver::BDD)]
+    #[arg(short, long, value_enum, default_value_t = Solver::default(), global = true)]
     solver: Solver,
-    input: String,
+    // print a distinguishing guarded string on failure
+    #[arg(short, long, global = true)]
+    witness: bool,
+    // minimize k2 automata before comparing them
+    #[arg(short, long, global = true)]
+    minimize: bool,
+    #[arg(short, long, value_enum, default_value_t = Format::Text, global = true)]
+    format: Format,
+    // print solver and backend counters
+    #[arg(long, global = true)]
+    stats: bool,
+    // print the k2 automata as structured expressions
+    #[arg(short, long)]
+    decompile: bool,
+    // write a bisimulation certificate on success
+    #[arg(long)]
+    certificate: Option<String>,
+    // treat the input as a certificate and check it
+    #[arg(long)]
+    check_certificate: bool,
+    // give up after this many seconds
+    #[arg(long, global = true, value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+    // give up after exploring this many state pairs
+    #[arg(long, global = true)]
+    max_pairs: Option<usize>,
+    // give up when CUDD holds more live nodes
+    #[arg(long, global = true)]
+    max_nodes: Option<usize>,
+    // give up when a SAT call needs more conflicts
+    #[arg(long, global = true)]
+    max_conflicts: Option<usize>,
+    // CUDD dynamic variable reordering method
+    #[arg(long, value_enum, default_value_t = Reorder::None, global = true)]
+    reorder: Reorder,
+    // create test variables in this order: `static` or a comma-separated list
+    #[arg(long, global = true, value_parser = parse_var_order)]
+    var_order: Option<VarOrder>,
+    // with solver auto, use the explicit backend up to this many test variables
+    #[arg(long, default_value_t = 12, global = true, value_parser = parse_explicit_vars)]
+    explicit_vars: usize,
+    #[arg(required = true)]
+    input: Option<String>,
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let secs: f64 = s
+        .parse()
+        .map_err(|_| format!("invalid number of seconds `{}`", s))?;
+    Duration::try_from_secs_f64(secs).map_err(|e| e.to_string())
+}
+
+fn parse_explicit_vars(s: &str) -> Result<usize, String> {
+    let n: usize = s.parse().map_err(|_| format!("invalid number `{}`", s))?;
+    if n > ExplicitGkat::MAX_VARS {
+        return Err(format!("at most {} variables", ExplicitGkat::MAX_VARS));
+    }
+    Ok(n)
+}
+
+fn parse_var_order(s: &str) -> Result<VarOrder, String> {
+    if s == "static" {
+        return Ok(VarOrder::Static);
+    }
+    let vars: Vec<String> = s.split(',').map(|x| x.trim().to_string()).collect();
+    if vars.iter().any(|x| x.is_empty()) {
+        return Err(format!("invalid variable order `{}`", s));
+    }
+    Ok(VarOrder::Explicit(vars))
+}
+
+impl Args {
+    // Variables of the explicit backend, when the solver takes it for input.
+    fn explicit_size(&self, input: &parsing::Input) -> Result<Option<usize>, InputError> {
+        let max = match self.solver {
+            Solver::Auto => self.explicit_vars,
+            Solver::Explicit => ExplicitGkat::MAX_VARS,
+            _ => return Ok(None),
+        };
+        let guards = input_guards(input);
+        let extra = self.var_order.as_ref().map_or(vec![], |o| o.vars(&guards));
+        match ExplicitGkat::size_for(&guards, extra, max) {
+            None if self.solver == Solver::Explicit => {
+                let max = ExplicitGkat::MAX_VARS;
+                let e = format!("more than {} test variables for solver explicit", max);
+                Err(InputError::Invalid(e))
+            }
+            size => Ok(size),
+        }
+    }
+
+    // Exit on ordering flags the solver would silently ignore.
+    fn check_ordering(&self) {
+        let unsupported = |msg: &str| {
+            Args::command()
+                .error(ErrorKind::ArgumentConflict, msg)
+                .exit()
+        };
+        if self.reorder != Reorder::None && !self.solver.reorders() {
+            unsupported("--reorder needs --solver bdd");
+        }
+        if self.var_order.is_some() && !self.solver.orders_vars() {
+            unsupported("--var-order needs a BDD solver");
+        }
+    }
+
+    fn limits(&self) -> Limits {
+        Limits {
+            timeout: self.timeout,
+            max_pairs: self.max_pairs,
+            max_nodes: self.max_nodes,
+            max_conflicts: self.max_conflicts,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    // check every input file under the given directories or globs
+    Batch {
+        // number of worker threads
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+}
+
+// Why an input file has no report.
+enum InputError {
+    Read(String),
+    // unparsable, or asking for what the options cannot do
+    Invalid(String),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Read(e) | InputError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// Outcome of checking one input file.
+struct Report {
+    name: &'static str,
+    // backend that ran, `explicit` when it took over from -s
+    solver: String,
+    expected: bool,
+    result: bool,
+    // counters of the solver and the gkat
+    stats: Stats,
+    // limit that stopped the check, making result meaningless
+    unknown: Option<Limit>,
+    // extra output such as witnesses, in order
+    fields: Vec<(String, String)>,
+}
+
+impl Report {
+    fn add_witness(&mut self, w: Option<Witness>) -> bool {
+        match w {
+            Some(w) => {
+                self.fields.push(("witness".to_string(), w.to_string()));
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn add_trace(&mut self, s: Option<GuardedString>) -> bool {
+        match s {
+            Some(s) => {
+                self.fields.push(("trace".to_string(), s.to_string()));
+                false
+            }
+            None => true,
+        }
+    }
+}
+
+fn check_k1<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    input: parsing::Input,
+    args: &Args,
+    report: &mut Report,
+) -> bool {
+    for b in input.constraints {
+        let b = gkat.from_bexp(b);
+        gkat.add_constraint(&b);
+    }
+    let mut solver = kernel1::Solver::new();
+    solver.set_limits(&args.limits());
+    if args.certificate.is_some() {
+        solver.record_relation();
+    }
+    let hyps = Hypotheses::from_raw(gkat, input.hyps);
+    solver.set_hypotheses(hyps);
+    let (exp1, exp2) = match &input.goal {
+        Goal::Equiv(exp1, exp2) | Goal::Includes(exp1, exp2) => (exp1.clone(), exp2.clone()),
+        Goal::Triple(pre, exp, post) => {
+            let pre = gkat.from_bexp(pre.clone());
+            let exp = gkat.from_exp(exp.clone());
+            let post = gkat.from_bexp(post.clone());
+            let holds = solver.triple_iter(gkat, &pre, &exp, &post);
+            report.stats.solver = solver.stats.clone();
+            return match holds {
+                Ok(true) => true,
+                // failing triples always report a trace
+                Ok(false) => report.add_trace(solver.triple_witness(gkat, &pre, &exp, &post)),
+                Err(limit) => {
+                    report.unknown = Some(limit);
+                    false
+                }
+            };
+        }
+    };
+    let exp1 = gkat.from_exp(exp1);
+    let exp2 = gkat.from_exp(exp2);
+    let result = match input.goal {
+        Goal::Equiv(..) if !args.witness => solver.equiv_iter(gkat, &exp1, &exp2),
+        Goal::Includes(..) if !args.witness => solver.incl_iter(gkat, &exp1, &exp2),
+        Goal::Equiv(..) => solver.equiv_witness(gkat, &exp1, &exp2).map(|w| report.add_witness(w)),
+        Goal::Includes(..) => solver.incl_witness(gkat, &exp1, &exp2).map(|w| report.add_witness(w)),
+        Goal::Triple(..) => unreachable!(),
+    };
+    report.stats.solver = solver.stats.clone();
+    let result = match result {
+        Ok(result) => result,
+        Err(limit) => {
+            report.unknown = Some(limit);
+            return false;
+        }
+    };
+    // certificates only cover equivalence
+    if let (Goal::Equiv(..), true, Some(path)) = (&input.goal, result, &args.certificate) {
+        // the relation is recorded whenever a certificate is requested
+        if let Some(cert) = solver.certificate(gkat, &exp1, &exp2) {
+            fs::write(path, cert.to_string()).expect("cannot write certificate");
+        }
+    }
+    result
+}
+
+fn check_k2<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    input: parsing::Input,
+    args: &Args,
+    report: &mut Report,
+) -> bool {
+    for b in input.constraints {
+        let b = gkat.from_bexp(b);
+        gkat.add_constraint(&b);
+    }
+    let mut solver = kernel2::Solver::new();
+    solver.set_limits(&args.limits());
+    if args.certificate.is_some() {
+        solver.record_relation();
+    }
+    let hyps = Hypotheses::from_raw(gkat, input.hyps);
+    solver.set_hypotheses(hyps);
+    let (exp1, exp2) = match &input.goal {
+        Goal::Equiv(exp1, exp2) | Goal::Includes(exp1, exp2) => (exp1.clone(), exp2.clone()),
+        Goal::Triple(pre, exp, post) => {
+            let pre = gkat.from_bexp(pre.clone());
+            let exp = gkat.from_exp(exp.clone());
+            let post = gkat.from_bexp(post.clone());
+            let holds = solver.triple_iter(gkat, &pre, &exp, &post);
+            report.stats.solver = solver.stats.clone();
+            return match holds {
+                Ok(true) => true,
+                // failing triples always report a trace
+                Ok(false) => report.add_trace(solver.triple_witness(gkat, &pre, &exp, &post)),
+                Err(limit) => {
+                    report.unknown = Some(limit);
+                    false
+                }
+            };
+        }
+    };
+    let exp1 = gkat.from_exp(exp1);
+    let exp2 = gkat.from_exp(exp2);
+    let (mut i, mut m) = solver.mk_automaton(gkat, &exp1);
+    let (mut j, mut n) = solver.mk_automaton(gkat, &exp2);
+    report.unknown = solver.limit_hit(gkat);
+    if report.unknown.is_some() {
+        report.stats.solver = solver.stats.clone();
+        return false;
+    }
+    if args.minimize {
+        let (s0, t0) = (m.size().0 + n.size().0, m.size().1 + n.size().1);
+        (i, m) = solver.minimize(gkat, i, &m);
+        (j, n) = solver.minimize(gkat, j, &n);
+        let (s1, t1) = (m.size().0 + n.size().0, m.size().1 + n.size().1);
+        let msg = format!("-{} states, -{} transitions", s0 - s1, t0 - t1);
+        report.fields.push(("minimized".to_string(), msg));
+    }
+    if args.decompile {
+        for (name, st, a) in [("decompiled1", i, &m), ("decompiled2", j, &n)] {
+            let msg = match solver.decompile(gkat, st, a) {
+                Ok(exp) => gkat.to_exp(&exp).to_string(),
+                Err(e) => e.to_string(),
+            };
+            report.fields.push((name.to_string(), msg));
+        }
+    }
+    let result = match input.goal {
+        Goal::Equiv(..) if !args.witness => solver.equiv_iter(gkat, i, j, &m, &n),
+        Goal::Includes(..) if !args.witness => solver.incl_iter(gkat, i, j, &m, &n),
+        Goal::Equiv(..) => solver.equiv_witness(gkat, i, j, &m, &n).map(|w| report.add_witness(w)),
+        Goal::Includes(..) => solver.incl_witness(gkat, i, j, &m, &n).map(|w| report.add_witness(w)),
+        Goal::Triple(..) => unreachable!(),
+    };
+    report.stats.solver = solver.stats.clone();
+    let result = match result {
+        Ok(result) => result,
+        Err(limit) => {
+            report.unknown = Some(limit);
+            return false;
+        }
+    };
+    // certificates only cover equivalence
+    if let (Goal::Equiv(..), true, Some(path)) = (&input.goal, result, &args.certificate) {
+        // the relation is recorded whenever a certificate is requested
+        if let Some(cert) = solver.certificate(gkat, i, j, &m, &n) {
+            fs::write(path, cert.to_string()).expect("cannot write certificate");
+        }
+    }
+    result
+}
+
+fn check_cert<B: BExp, G: Gkat<B>>(gkat: &mut G, cert: parsing::Certificate) {
+    let result = certificate::Checker::new().check(gkat, cert);
+    // the explicit backend gives up past its number of variables
+    if let Some(limit) = gkat.exhausted() {
+        exit_with(Status::Unknown, limit);
+    }
+    match result {
+        Ok(()) => println!("certificate    = valid"),
+        Err(msg) => {
+            println!("certificate    = invalid ({})", msg);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn check_input<B: BExp, G: Gkat<B>>(gkat: &mut G, input: parsing::Input, args: &Args) -> Report {
+    let name = match input.goal {
+        Goal::Equiv(..) => "equiv",
+        Goal::Includes(..) => "incl",
+        Goal::Triple(..) => "triple",
+    };
+    let mut report = Report {
+        name,
+        solver: value_name(args.solver.backend()),
+        expected: input.expected,
+        result: false,
+        stats: Stats::default(),
+        unknown: None,
+        fields: vec![],
+    };
+    // a batch worker reuses gkat, so count only the work for this input
+    let before = gkat.stats();
+    gkat.set_limits(&args.limits());
+    gkat.set_reordering(args.reorder);
+    if let Some(order) = &args.var_order {
+        for x in order.vars(&input_guards(&input)) {
+            gkat.mk_var(x);
+        }
+    }
+    report.result = match args.kernel {
+        Kernel::K1 => check_k1(gkat, input, args, &mut report),
+        Kernel::K2 => check_k2(gkat, input, args, &mut report),
+    };
+    report.stats.gkat = gkat.stats().since(&before);
+    report
+}
+
+// Check input with the explicit backend for n variables.
+fn check_explicit(n: usize, input: parsing::Input, args: &Args) -> Report {
+    let mut report = check_input(&mut ExplicitGkat::new(n), input, args);
+    report.solver = "explicit".to_string();
+    report
+}
+
+fn read_input(path: &str, args: &Args) -> Result<parsing::Input, InputError> {
+    let file = fs::read_to_string(path)
+        .map_err(|e| InputError::Read(format!("cannot read {}: {}", path, e)))?;
+    let input = parse(file).map_err(|e| InputError::Invalid(e.in_file(path).to_string()))?;
+    // the certificate checker knows nothing about hypotheses or constraints
+    let assumptions = !input.hyps.is_empty() || !input.constraints.is_empty();
+    if args.certificate.is_some() && assumptions {
+        let e = "certificates are not supported with hypotheses or constraints";
+        return Err(InputError::Invalid(e.to_string()));
+    }
+    Ok(input)
+}
+
+fn exit_with<T: fmt::Display>(status: Status, e: T) -> ! {
+    eprintln!("error: {}", e);
+    std::process::exit(status.code());
 }
 
 fn main() {
     let args = Args::parse();
-    let file = fs::read_to_string(args.input).expect("cannot read file");
-    let (exp1, exp2, b) = parse(file);
-    let result = match args.kernel {
-        Kernel::K1 => match args.solver {
-            Solver::BDD => {
-                let mut gkat = BDDGkat::new();
-                let mut solver = kernel1::Solver::new();
-                let exp1 = gkat.from_exp(exp1);
-                let exp2 = gkat.from_exp(exp2);
-                solver.equiv_iter(&mut gkat, &exp1, &exp2)
+    args.check_ordering();
+    if let Some(Command::Batch { jobs, paths }) = &args.command {
+        if args.certificate.is_some() || args.check_certificate {
+            exit_with(Status::InputError, "certificates are not supported in batch mode");
+        }
+        let status = batch::run(paths, *jobs, &args)
+            .unwrap_or_else(|e| exit_with(Status::ReadError, e));
+        std::process::exit(status.code());
+    }
+    let path = args.input.as_ref().unwrap();
+    if args.check_certificate {
+        let file = match fs::read_to_string(path) {
+            Ok(file) => file,
+            Err(e) => exit_with(Status::ReadError, format!("cannot read {}: {}", path, e)),
+        };
+        let cert = parse_certificate(file)
+            .unwrap_or_else(|e| exit_with(Status::InputError, e.in_file(path)));
+        match args.solver.backend() {
+            #[cfg(feature = "cudd")]
+            Solver::BDD => check_cert(&mut BDDGkat::new(), cert),
+            #[cfg(feature = "sat")]
+            Solver::SAT => check_cert(&mut SATGkat::new(), cert),
+            #[cfg(feature = "sat")]
+            Solver::AIG => check_cert(&mut AIGGkat::new(), cert),
+            #[cfg(feature = "sat")]
+            Solver::Hybrid => check_cert(&mut HybridGkat::new(), cert),
+            Solver::PureBDD => check_cert(&mut PureBDDGkat::new(), cert),
+            Solver::Explicit => check_cert(&mut ExplicitGkat::new(0), cert),
+            Solver::Auto => unreachable!(),
+        }
+        return;
+    }
+    let start = Instant::now();
+    let report = read_input(path, &args).and_then(|input| {
+        Ok(match (args.explicit_size(&input)?, args.solver.backend()) {
+            (Some(n), _) => check_explicit(n, input, &args),
+            #[cfg(feature = "cudd")]
+            (None, Solver::BDD) => check_input(&mut BDDGkat::new(), input, &args),
+            #[cfg(feature = "sat")]
+            (None, Solver::SAT) => check_input(&mut SATGkat::new(), input, &args),
+            #[cfg(feature = "sat")]
+            (None, Solver::AIG) => check_input(&mut AIGGkat::new(), input, &args),
+            #[cfg(feature = "sat")]
+            (None, Solver::Hybrid) => check_input(&mut HybridGkat::new(), input, &args),
+            (None, Solver::PureBDD) => check_input(&mut PureBDDGkat::new(), input, &args),
+            // explicit_size took these
+            (None, Solver::Auto | Solver::Explicit) => unreachable!(),
+        })
+    });
+    let time = start.elapsed();
+    let status = Status::of(&report);
+    match (args.format, &report) {
+        (Format::Text, Ok(report)) => {
+            if report.solver != value_name(args.solver) {
+                println!("{:<15}= {}", "solver", report.solver);
             }
-            Solver::SAT => {
-                let mut gkat = SATGkat::new();
-                let mut solver = kernel1::Solver::new();
-                let exp1 = gkat.from_exp(exp1);
-                let exp2 = gkat.from_exp(exp2);
-                solver.equiv_iter(&mut gkat, &exp1, &exp2)
+            for (key, value) in &report.fields {
+                println!("{:<15}= {}", key, value);
             }
-        },
-        Kernel::K2 => match args.solver {
-            Solver::BDD => {
-                let mut gkat = BDDGkat::new();
-                let mut solver = kernel2::Solver::new();
-                let exp1 = gkat.from_exp(exp1);
-                let exp2 = gkat.from_exp(exp2);
-                let (i, m) = solver.mk_automaton(&mut gkat, &exp1);
-                let (j, n) = solver.mk_automaton(&mut gkat, &exp2);
-                solver.equiv_iter(&mut gkat, i, j, &m, &n)
+            println!(
+                "{:<15}= {}",
+                format!("{}_expected", report.name),
+                report.expected
+            );
+            let result = match report.unknown {
+                Some(limit) => format!("unknown ({})", limit),
+                None => report.result.to_string(),
+            };
+            println!("{:<15}= {}", format!("{}_result", report.name), result);
+            if args.stats {
+                println!("{}", report.stats);
             }
-            Solver::SAT => {
-                let mut gkat = SATGkat::new();
-                let mut solver = kernel2::Solver::new();
-                let exp1 = gkat.from_exp(exp1);
-                let exp2 = gkat.from_exp(exp2);
-                let (i, m) = solver.mk_automaton(&mut gkat, &exp1);
-                let (j, n) = solver.mk_automaton(&mut gkat, &exp2);
-                solver.equiv_iter(&mut gkat, i, j, &m, &n)
+            if status == Status::Mismatch {
+                eprintln!("error: result does not match the expected value");
             }
-        },
-    };
-
-    println!("equiv_expected = {}", b);
-    println!("equiv_result   = {}", result);
-    assert!(b == result);
+        }
+        (Format::Text, Err(e)) => eprintln!("error: {}", e),
+        (_, _) => {
+            print_header(&args);
+            print_record(path, time, &report, &args);
+        }
+    }
+    std::process::exit(status.code());
 }
@@ -0,0 +1,184 @@
+use crate::{Args, InputError, Report};
+use clap::ValueEnum;
+use rust_gkat::syntax::Stats;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text, // Human readable
+    Json, // One object per line
+    Csv,  // Header and one row per input
+}
+
+// Outcome of one input, ordered by severity for batch runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Equivalent,
+    NotEquivalent,
+    Unknown,
+    Mismatch,
+    InputError,
+    ReadError,
+}
+
+impl Status {
+    pub fn of(report: &Result<Report, InputError>) -> Self {
+        match report {
+            Ok(r) if r.unknown.is_some() => Status::Unknown,
+            Ok(r) if r.expected != r.result => Status::Mismatch,
+            Ok(r) if r.result => Status::Equivalent,
+            Ok(_) => Status::NotEquivalent,
+            Err(InputError::Read(_)) => Status::ReadError,
+            Err(InputError::Invalid(_)) => Status::InputError,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Equivalent => "equivalent",
+            Status::NotEquivalent => "not-equivalent",
+            Status::Unknown => "unknown",
+            Status::Mismatch => "mismatch",
+            Status::InputError => "input-error",
+            Status::ReadError => "read-error",
+        }
+    }
+
+    // Process exit code; 2 is left to clap for usage errors.
+    pub fn code(self) -> i32 {
+        match self {
+            Status::Equivalent => 0,
+            Status::NotEquivalent => 1,
+            Status::Mismatch => 3,
+            Status::InputError => 4,
+            Status::Unknown => 5,
+            Status::ReadError => 6,
+        }
+    }
+}
+
+// Name of an option value as written on the command line.
+pub fn value_name<T: ValueEnum>(v: T) -> String {
+    v.to_possible_value().unwrap().get_name().to_string()
+}
+
+const COLUMNS: [&str; 12] = [
+    "file", "goal", "kernel", "solver", "expected", "result", "status", "reason", "time", "pairs",
+    "witness", "error",
+];
+
+// Columns of a record, followed by the counters with --stats.
+fn columns(args: &Args) -> Vec<&'static str> {
+    let mut columns = COLUMNS.to_vec();
+    if args.stats {
+        let counters = Stats::default().counters().into_iter().map(|(k, _)| k);
+        columns.extend(counters.filter(|k| *k != "pairs"));
+    }
+    columns
+}
+
+pub fn print_header(args: &Args) {
+    if args.format == Format::Csv {
+        println!("{}", columns(args).join(","));
+    }
+}
+
+// Print one input as a json or csv record.
+pub fn print_record(
+    file: &str,
+    time: Duration,
+    report: &Result<Report, InputError>,
+    args: &Args,
+) {
+    let mut values: Vec<Option<String>> = vec![
+        Some(file.to_string()),
+        None,
+        Some(value_name(args.kernel)),
+        Some(value_name(args.solver)),
+        None,
+        None,
+        Some(Status::of(report).name().to_string()),
+        None,
+        Some(format!("{:.6}", time.as_secs_f64())),
+        None,
+        None,
+        None,
+    ];
+    match report {
+        Ok(r) => {
+            values[1] = Some(r.name.to_string());
+            values[3] = Some(r.solver.clone());
+            values[4] = Some(r.expected.to_string());
+            match r.unknown {
+                Some(limit) => values[7] = Some(limit.to_string()),
+                None => values[5] = Some(r.result.to_string()),
+            }
+            values[9] = Some(r.stats.solver.pairs.to_string());
+            values[10] = r
+                .fields
+                .iter()
+                .find(|(k, _)| k == "witness" || k == "trace")
+                .map(|(_, v)| v.clone());
+        }
+        Err(e) => values[11] = Some(e.to_string()),
+    }
+    let columns = columns(args);
+    for k in &columns[COLUMNS.len()..] {
+        let counters = report.as_ref().ok().map(|r| r.stats.counters());
+        let n = counters.and_then(|c| c.iter().find(|(name, _)| name == k).map(|(_, n)| *n));
+        values.push(n.map(|n| n.to_string()));
+    }
+    match args.format {
+        Format::Text => unreachable!(),
+        Format::Json => {
+            let fields: Vec<String> = columns
+                .iter()
+                .zip(&values)
+                .map(|(k, v)| {
+                    let v = match v {
+                        None => "null".to_string(),
+                        // booleans and numbers are written bare
+                        Some(v) if matches!(*k, "expected" | "result" | "time" | "pairs") => {
+                            v.clone()
+                        }
+                        Some(v) if !COLUMNS.contains(k) => v.clone(),
+                        Some(v) => json_string(v),
+                    };
+                    format!("\"{}\":{}", k, v)
+                })
+                .collect();
+            println!("{{{}}}", fields.join(","));
+        }
+        Format::Csv => {
+            let fields: Vec<String> = values
+                .iter()
+                .map(|v| csv_field(v.as_deref().unwrap_or("")))
+                .collect();
+            println!("{}", fields.join(","));
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
@@ -0,0 +1,123 @@
+use lalrpop_util::{ParseError as RawError, lexer::Token};
+use std::fmt;
+
+// Parse or validation error located in the source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub file: Option<String>,
+    // 1-based position
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub expected: Vec<String>,
+    // source line containing the error
+    pub snippet: String,
+}
+
+impl ParseError {
+    pub fn new(src: &str, offset: usize, message: String, expected: Vec<String>) -> Self {
+        let offset = offset.min(src.len());
+        let start = src[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let end = src[offset..].find('\n').map_or(src.len(), |i| offset + i);
+        ParseError {
+            file: None,
+            line: src[..offset].matches('\n').count() + 1,
+            column: src[start..offset].chars().count() + 1,
+            message,
+            expected,
+            snippet: src[start..end].trim_end().to_string(),
+        }
+    }
+
+    pub fn from_raw(src: &str, e: RawError<usize, Token<'_>, (usize, &'static str)>) -> Self {
+        match e {
+            RawError::InvalidToken { location } => {
+                ParseError::new(src, location, "invalid token".to_string(), vec![])
+            }
+            RawError::UnrecognizedEof { location, expected } => {
+                let msg = "unexpected end of input".to_string();
+                ParseError::new(src, location, msg, pretty_expected(expected))
+            }
+            RawError::UnrecognizedToken {
+                token: (l, t, _),
+                expected,
+            } => {
+                // a name right after an opening parenthesis is a keyword
+                let keyword = src[..l].trim_end().ends_with('(')
+                    && t.1.chars().all(|c| c.is_ascii_alphanumeric());
+                let msg = if keyword && KEYWORDS.contains(&t.1) {
+                    format!("unexpected keyword `{}`", t.1)
+                } else if keyword {
+                    format!("unknown keyword `{}`", t.1)
+                } else {
+                    format!("unexpected token `{}`", t.1)
+                };
+                ParseError::new(src, l, msg, pretty_expected(expected))
+            }
+            RawError::ExtraToken { token: (l, t, _) } => {
+                let msg = format!("extra token `{}`", t.1);
+                ParseError::new(src, l, msg, vec![])
+            }
+            RawError::User { error: (l, msg) } => ParseError::new(src, l, msg.to_string(), vec![]),
+        }
+    }
+
+    pub fn in_file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_string());
+        self
+    }
+}
+
+// Keywords of spec.lalrpop.
+const KEYWORDS: &[&str] = &[
+    "and",
+    "or",
+    "not",
+    "test",
+    "seq",
+    "if",
+    "while",
+    "equiv",
+];
+
+// Turn lalrpop's quoted terminals into readable names.
+fn pretty_expected(expected: Vec<String>) -> Vec<String> {
+    let mut names: Vec<String> = expected
+        .iter()
+        .map(|s| {
+            if s.starts_with("r#") {
+                let name = if s.contains("a-z") {
+                    "identifier"
+                } else {
+                    "number"
+                };
+                name.to_string()
+            } else {
+                s.trim_matches('"').to_string()
+            }
+        })
+        .collect();
+    names.dedup();
+    names
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self.file.as_deref().unwrap_or("<input>");
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            file, self.line, self.column, self.message
+        )?;
+        if !self.expected.is_empty() {
+            write!(f, ", expected one of {}", self.expected.join(" "))?;
+        }
+        let gutter = " ".repeat(self.line.to_string().len());
+        writeln!(f)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.snippet)?;
+        write!(f, "{} | {}^", gutter, " ".repeat(self.column - 1))
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -1,4 +1,13 @@
+mod error;
 mod parser;
+mod printer;
 mod raw;
+// The parser of the web page, to read back what the printer emits for it.
+#[cfg(test)]
+#[allow(dead_code, clippy::all)]
+#[path = "../../web_wasm/src/parsing/custom_parser.rs"]
+mod web_parser;
+pub use error::*;
 pub use parser::*;
+pub use printer::*;
 pub use raw::*;
@@ -1,14 +1,31 @@
-use crate::parsing::raw::Exp;
+use crate::parsing::{
+    error::ParseError,
+    raw::{Certificate, Exp, Input},
+};
 
 use lalrpop_util::lalrpop_mod;
 
 lalrpop_mod!(
-    #[allow(unused_imports)]
+    #[allow(unused_imports, clippy::all)]
     #[rustfmt::skip]
     pub spec);
 
-pub fn parse(s: String) -> (Exp, Exp, bool) {
-    spec::InputParser::new().parse(&s).unwrap()
+pub fn parse(s: String) -> Result<Input, ParseError> {
+    spec::InputParser::new()
+        .parse(&s)
+        .map_err(|e| ParseError::from_raw(&s, e))
+}
+
+pub fn parse_exp(s: &str) -> Result<Exp, ParseError> {
+    spec::ExpParser::new()
+        .parse(s)
+        .map_err(|e| ParseError::from_raw(s, e))
+}
+
+pub fn parse_certificate(s: String) -> Result<Certificate, ParseError> {
+    spec::CertificateParser::new()
+        .parse(&s)
+        .map_err(|e| ParseError::from_raw(&s, e))
 }
 
 #[test]
@@ -39,4 +56,61 @@ pub fn test() {
         "{:?}",
         spec::ExpParser::new().parse("(while abd\n b)\n").unwrap()
     );
+
+    // errors are located and name what was expected
+    let cases = [
+        ("(seq p1\n  (and))", 2, 4, "unexpected keyword `and`"),
+        (
+            "(if (and) p1 p2)",
+            1,
+            5,
+            "`and` expects at least 2 operands",
+        ),
+        ("(seq p1 (foo p2))", 1, 10, "unknown keyword `foo`"),
+        ("(seq p1 $)", 1, 9, "invalid token"),
+        ("(while b1", 1, 10, "unexpected end of input"),
+    ];
+    for (src, line, column, message) in cases {
+        let e = parse_exp(src).unwrap_err();
+        assert_eq!(
+            (e.line, e.column, e.message.as_str()),
+            (line, column, message)
+        );
+    }
+    let e = parse_certificate("(certificate (start 99999999999999999999999 0))".to_string());
+    let e = e.unwrap_err();
+    assert_eq!((e.column, e.message.as_str()), (21, "number out of range"));
+    // directive words remain valid names
+    let srcs = [
+        "(hypothesis triple includes holds) includes (seq includes constraint) (equiv 1)",
+        "(constraint (or hypothesis holds)) (triple holds triple holds) (holds 1)",
+    ];
+    for src in srcs {
+        parse(src.to_string()).unwrap();
+    }
+    let cases = [
+        ("p1 p1 (includes p1)", 7, "expected 0 or 1"),
+        ("p1 p1 (foo 1)", 7, "expected `equiv` or `includes`"),
+        ("(foo 1) p1 p1 (equiv 1)", 1, "unknown directive"),
+        ("(hypothesis 1 (test 1) 1) p1 p1 (equiv 1)", 1, "`hypothesis` expects an action"),
+        ("(triple 1 p1 1) (equiv 1)", 17, "expected `holds`"),
+        ("p1 p1 (equiv 1) p1", 17, "unexpected input after the goal"),
+    ];
+    for (src, column, message) in cases {
+        let e = parse(src.to_string()).unwrap_err();
+        assert_eq!((e.column, e.message.as_str()), (column, message));
+    }
+    // certificate words remain valid names
+    parse("(seq start pair) (seq start pair) (equiv 1)".to_string()).unwrap();
+    parse("(if eps state trans) (if eps state trans) (equiv 1)".to_string()).unwrap();
+    let cert = "(certificate (start 0 0) (state 0 (eps state) (trans pair exp 0)) (exp 0 state))";
+    parse_certificate(cert.to_string()).unwrap();
+    let e = parse_certificate("(certificate (begin 0 0))".to_string()).unwrap_err();
+    assert_eq!((e.column, e.message.as_str()), (14, "expected `start` or `pair`"));
+    let e = parse("p1 p1 (equiv 2)".to_string()).unwrap_err();
+    assert_eq!(e.expected, vec!["0", "1"]);
+    assert!(
+        e.to_string()
+            .ends_with("1 | p1 p1 (equiv 2)\n  |              ^")
+    );
 }
@@ -0,0 +1,355 @@
+use crate::{
+    hash::{HashSet, HashSetExt},
+    parsing::raw::*,
+};
+use std::fmt;
+
+// Collect the operands of a right-nested chain such as (seq a (seq b c)).
+fn chain<'a, T>(
+    first: &'a T,
+    rest: &'a T,
+    next: fn(&'a T) -> Option<(&'a T, &'a T)>,
+) -> Vec<&'a T> {
+    let mut ops = vec![first];
+    let mut cur = rest;
+    while let Some((l, r)) = next(cur) {
+        ops.push(l);
+        cur = r;
+    }
+    ops.push(cur);
+    ops
+}
+
+impl fmt::Display for BExp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use BExp::*;
+        match self {
+            Zero => write!(f, "0"),
+            One => write!(f, "1"),
+            PBool(s) => write!(f, "{}", s),
+            And(b1, b2) => {
+                let ops = chain(&**b1, &**b2, |b| match b {
+                    And(l, r) => Some((l, r)),
+                    _ => None,
+                });
+                write!(f, "(and")?;
+                ops.iter().try_for_each(|b| write!(f, " {}", b))?;
+                write!(f, ")")
+            }
+            Or(b1, b2) => {
+                let ops = chain(&**b1, &**b2, |b| match b {
+                    Or(l, r) => Some((l, r)),
+                    _ => None,
+                });
+                write!(f, "(or")?;
+                ops.iter().try_for_each(|b| write!(f, " {}", b))?;
+                write!(f, ")")
+            }
+            Not(b) => write!(f, "(not {})", b),
+        }
+    }
+}
+
+impl fmt::Display for Exp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Exp::*;
+        match self {
+            Act(s) => write!(f, "{}", s),
+            Seq(p1, p2) => {
+                let ops = chain(&**p1, &**p2, |p| match p {
+                    Seq(l, r) => Some((l, r)),
+                    _ => None,
+                });
+                write!(f, "(seq")?;
+                ops.iter().try_for_each(|p| write!(f, " {}", p))?;
+                write!(f, ")")
+            }
+            Ifte(b, p1, p2) => write!(f, "(if {} {} {})", b, p1, p2),
+            Test(b) => write!(f, "(test {})", b),
+            While(b, p) => write!(f, "(while {} {})", b, p),
+        }
+    }
+}
+
+// Concrete syntax of a printed expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    // (if b p q), as read by parse_exp
+    SExp,
+    // if b { P } else { Q }, as on the web page, which takes actions with
+    // an uppercase and tests with a lowercase first letter; names are
+    // adjusted to that, see WebNames
+    Web,
+}
+
+// Print exp in the given syntax, on one line or indented by two spaces.
+pub fn print_exp(exp: &Exp, syntax: Syntax, indent: bool) -> String {
+    let mut out = String::new();
+    match syntax {
+        Syntax::SExp => sexp(exp, indent.then_some(0), &mut out),
+        Syntax::Web => {
+            let names = WebNames::new(exp);
+            web(exp, &names, indent.then_some(0), &mut out)
+        }
+    }
+    out
+}
+
+// Operands of a sequence, flattened in both directions.
+fn seq_ops<'a>(exp: &'a Exp, ops: &mut Vec<&'a Exp>) {
+    match exp {
+        Exp::Seq(p1, p2) => {
+            seq_ops(p1, ops);
+            seq_ops(p2, ops);
+        }
+        _ => ops.push(exp),
+    }
+}
+
+// Start a new line at depth, or a space in compact layout.
+fn newline(depth: Option<usize>, out: &mut String) {
+    match depth {
+        Some(d) => {
+            out.push('\n');
+            out.push_str(&"  ".repeat(d));
+        }
+        None => out.push(' '),
+    }
+}
+
+fn sexp(exp: &Exp, depth: Option<usize>, out: &mut String) {
+    let inner = depth.map(|d| d + 1);
+    match exp {
+        Exp::Act(_) | Exp::Test(_) => out.push_str(&exp.to_string()),
+        Exp::Seq(..) => {
+            let mut ops = vec![];
+            seq_ops(exp, &mut ops);
+            out.push_str("(seq");
+            for p in ops {
+                newline(inner, out);
+                sexp(p, inner, out);
+            }
+            out.push(')');
+        }
+        Exp::Ifte(b, p1, p2) => {
+            out.push_str(&format!("(if {}", b));
+            newline(inner, out);
+            sexp(p1, inner, out);
+            newline(inner, out);
+            sexp(p2, inner, out);
+            out.push(')');
+        }
+        Exp::While(b, p) => {
+            out.push_str(&format!("(while {}", b));
+            newline(inner, out);
+            sexp(p, inner, out);
+            out.push(')');
+        }
+    }
+}
+
+// Names of an expression as the web page takes them. A name with the
+// wrong first letter gets it in the right case, followed by an _ (which
+// names never contain) if another name of its kind already reads that
+// way, so that b and B stay two tests.
+struct WebNames {
+    acts: HashSet<String>,
+    tests: HashSet<String>,
+}
+
+impl WebNames {
+    fn new(exp: &Exp) -> Self {
+        let mut names = WebNames {
+            acts: HashSet::new(),
+            tests: HashSet::new(),
+        };
+        names.add_exp(exp);
+        names
+    }
+
+    fn add_exp(&mut self, exp: &Exp) {
+        match exp {
+            Exp::Act(a) => {
+                self.acts.insert(a.clone());
+            }
+            Exp::Seq(p1, p2) => {
+                self.add_exp(p1);
+                self.add_exp(p2);
+            }
+            Exp::Ifte(b, p1, p2) => {
+                self.add_bexp(b);
+                self.add_exp(p1);
+                self.add_exp(p2);
+            }
+            Exp::Test(b) => self.add_bexp(b),
+            Exp::While(b, p) => {
+                self.add_bexp(b);
+                self.add_exp(p);
+            }
+        }
+    }
+
+    fn add_bexp(&mut self, b: &BExp) {
+        match b {
+            BExp::PBool(s) => {
+                self.tests.insert(s.clone());
+            }
+            BExp::And(b1, b2) | BExp::Or(b1, b2) => {
+                self.add_bexp(b1);
+                self.add_bexp(b2);
+            }
+            BExp::Not(b) => self.add_bexp(b),
+            BExp::Zero | BExp::One => {}
+        }
+    }
+
+    fn act(&self, a: &str) -> String {
+        web_name(a, true, &self.acts)
+    }
+
+    fn test(&self, b: &str) -> String {
+        web_name(b, false, &self.tests)
+    }
+}
+
+fn web_name(s: &str, upper: bool, taken: &HashSet<String>) -> String {
+    let name = first_case(s, upper);
+    if name != s && taken.contains(&name) {
+        name + "_"
+    } else {
+        name
+    }
+}
+
+fn web(exp: &Exp, names: &WebNames, depth: Option<usize>, out: &mut String) {
+    let inner = depth.map(|d| d + 1);
+    // body of a block, on its own lines when indented
+    let block = |p: &Exp, out: &mut String| {
+        out.push('{');
+        newline(inner, out);
+        web(p, names, inner, out);
+        newline(depth, out);
+        out.push('}');
+    };
+    match exp {
+        Exp::Act(a) => out.push_str(&names.act(a)),
+        Exp::Test(b) => out.push_str(&format!("assert {}", web_bexp(b, names))),
+        Exp::Seq(..) => {
+            let mut ops = vec![];
+            seq_ops(exp, &mut ops);
+            for (i, p) in ops.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(';');
+                    newline(depth, out);
+                }
+                web(p, names, depth, out);
+            }
+        }
+        Exp::Ifte(b, p1, p2) => {
+            out.push_str(&format!("if {} ", web_bexp(b, names)));
+            block(p1, out);
+            out.push_str(" else ");
+            block(p2, out);
+        }
+        Exp::While(b, p) => {
+            out.push_str(&format!("while {} ", web_bexp(b, names)));
+            block(p, out);
+        }
+    }
+}
+
+// Name with its first letter in upper or lower case.
+fn first_case(s: &str, upper: bool) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if upper => c.to_uppercase().chain(chars).collect(),
+        Some(c) => c.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// Boolean expression with !, & and |; the web parser takes one binary
+// operator per level, so nested ones are parenthesized. It has no
+// constants, so 0 and 1 become a contradiction and a tautology.
+fn web_bexp(b: &BExp, names: &WebNames) -> String {
+    use BExp::*;
+    let operand = |b: &BExp| match b {
+        And(..) | Or(..) => format!("({})", web_bexp(b, names)),
+        _ => web_bexp(b, names),
+    };
+    match b {
+        Zero => "(b & !b)".to_string(),
+        One => "(b | !b)".to_string(),
+        PBool(s) => names.test(s),
+        And(b1, b2) => format!("{} & {}", operand(b1), operand(b2)),
+        Or(b1, b2) => format!("{} | {}", operand(b1), operand(b2)),
+        Not(b) => format!("!{}", operand(b)),
+    }
+}
+
+impl fmt::Display for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "(certificate")?;
+        writeln!(f, "  (start {} {})", self.start.0, self.start.1)?;
+        for (id, e) in &self.exps {
+            writeln!(f, "  (exp {} {})", id, e)?;
+        }
+        for st in &self.states {
+            write!(f, "  (state {} (eps {})", st.id, st.eps)?;
+            for (b, a, next) in &st.trans {
+                write!(f, "\n    (trans {} {} {})", b, a, next)?;
+            }
+            writeln!(f, ")")?;
+        }
+        for (i, j) in &self.pairs {
+            writeln!(f, "  (pair {} {})", i, j)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[test]
+fn test() {
+    use crate::{kernel1::Solver, parsing::parse_exp, syntax::*};
+    let src = "(seq p1 (seq (if (and b1 (or b2 (not b3))) (seq p2 p3) (test 1)) \
+               (while (not b1) (seq p4 (if b2 p5 (test 0))))))";
+    let exp = parse_exp(src).unwrap();
+    assert_eq!(
+        print_exp(&exp, Syntax::Web, false),
+        "P1; if b1 & (b2 | !b3) { P2; P3 } else { assert (b | !b) }; \
+         while !b1 { P4; if b2 { P5 } else { assert (b & !b) } }"
+    );
+    assert_eq!(
+        print_exp(&exp, Syntax::SExp, true).lines().nth(2),
+        Some("  (if (and b1 (or b2 (not b3)))")
+    );
+    let mut gkat = PureBDDGkat::new();
+    let mut solver = Solver::new();
+    let exp = gkat.from_exp(exp);
+    for indent in [false, true] {
+        let printed = gkat.print_exp(&exp, Syntax::SExp, indent);
+        let back = gkat.from_exp(parse_exp(&printed).unwrap());
+        assert_eq!(solver.equiv_iter(&mut gkat, &exp, &back), Ok(true));
+    }
+    // the web page reads its syntax back, with the names it takes
+    let src = "(seq P1 (seq (if (and b1 (or b2 (not b3))) (seq P2 P3) (test 1)) \
+               (while (not b1) (seq P4 (if b2 P5 (test (and b2 0)))))))";
+    let exp = gkat.from_exp(parse_exp(src).unwrap());
+    for indent in [false, true] {
+        let printed = gkat.print_exp(&exp, Syntax::Web, indent);
+        let back = super::web_parser::parse_expression(&printed).expect(&printed);
+        let back = gkat.from_exp(back);
+        assert_eq!(solver.equiv_iter(&mut gkat, &exp, &back), Ok(true));
+    }
+    // names differing only in the case of their first letter stay apart
+    let src = "(seq p (seq P (if b p (if B P (test (and b (not B)))))))";
+    let exp = parse_exp(src).unwrap();
+    assert_eq!(
+        print_exp(&exp, Syntax::Web, false),
+        "P_; P; if b { P_ } else { if b_ { P } else { assert b & !b_ } }"
+    );
+    // and read back as four names, which the printer keeps
+    let printed = print_exp(&exp, Syntax::Web, true);
+    let back = super::web_parser::parse_expression(&printed).unwrap();
+    assert_eq!(print_exp(&back, Syntax::Web, true), printed);
+}
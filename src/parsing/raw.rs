@@ -16,3 +16,138 @@ pub enum Exp {
     Test(BExp),
     While(BExp, Box<Exp>),
 }
+
+// Property checked by an input file.
+#[derive(Debug, Clone)]
+pub enum Goal {
+    Equiv(Exp, Exp),
+    // language of the first expression contained in the second
+    Includes(Exp, Exp),
+    // every terminating run from pre ends in post
+    Triple(BExp, Exp, BExp),
+}
+
+// Hoare hypothesis pre;act = pre;act;post.
+#[derive(Debug, Clone)]
+pub struct Hypothesis {
+    pub pre: BExp,
+    pub act: String,
+    pub post: BExp,
+}
+
+#[derive(Debug, Clone)]
+pub struct Input {
+    // atoms outside these constraints are impossible
+    pub constraints: Vec<BExp>,
+    pub hyps: Vec<Hypothesis>,
+    pub goal: Goal,
+    pub expected: bool,
+}
+
+// Top-level form of an input file, before its directives are told apart.
+#[derive(Debug, Clone)]
+pub enum Item {
+    Exp(Exp),
+    Equiv(bool),
+    // (name b)
+    Unary(String, BExp),
+    // (name pre p post)
+    Ternary(String, BExp, Exp, BExp),
+}
+
+// Constraints and hypotheses followed by a goal; end is the offset of the
+// end of input.
+pub fn items_input(items: Vec<(usize, Item)>, end: usize) -> Result<Input, (usize, &'static str)> {
+    let mut constraints = vec![];
+    let mut hyps = vec![];
+    let mut items = items.into_iter();
+    let (goal, expected) = loop {
+        let Some((l, item)) = items.next() else {
+            return Err((end, "expected a goal"));
+        };
+        match item {
+            Item::Unary(d, b) if d == "constraint" => constraints.push(b),
+            Item::Ternary(d, pre, Exp::Act(act), post) if d == "hypothesis" => {
+                hyps.push(Hypothesis { pre, act, post })
+            }
+            Item::Ternary(d, _, _, _) if d == "hypothesis" => {
+                return Err((l, "`hypothesis` expects an action"));
+            }
+            Item::Ternary(d, pre, p, post) if d == "triple" => match items.next() {
+                Some((l, Item::Unary(d, b))) if d == "holds" => {
+                    break (Goal::Triple(pre, p, post), bool(l, b)?);
+                }
+                Some((l, _)) => return Err((l, "expected `holds`")),
+                None => return Err((end, "expected `holds`")),
+            },
+            Item::Exp(p1) => match (items.next(), items.next()) {
+                (Some((_, Item::Exp(p2))), Some((_, Item::Equiv(b)))) => {
+                    break (Goal::Equiv(p1, p2), b);
+                }
+                (Some((_, Item::Exp(p2))), Some((l, Item::Unary(d, b)))) if d == "includes" => {
+                    break (Goal::Includes(p1, p2), bool(l, b)?);
+                }
+                (Some((_, Item::Exp(_))), Some((l, _))) => {
+                    return Err((l, "expected `equiv` or `includes`"));
+                }
+                (Some((_, Item::Exp(_))), None) => {
+                    return Err((end, "expected `equiv` or `includes`"));
+                }
+                (Some((l, _)), _) => return Err((l, "expected a second program")),
+                (None, _) => return Err((end, "expected a second program")),
+            },
+            _ => return Err((l, "unknown directive")),
+        }
+    };
+    match items.next() {
+        Some((l, _)) => Err((l, "unexpected input after the goal")),
+        None => Ok(Input {
+            constraints,
+            hyps,
+            goal,
+            expected,
+        }),
+    }
+}
+
+// Expected result of a goal.
+fn bool(l: usize, b: BExp) -> Result<bool, (usize, &'static str)> {
+    match b {
+        BExp::Zero => Ok(false),
+        BExp::One => Ok(true),
+        _ => Err((l, "expected 0 or 1")),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CertState {
+    pub id: u64,
+    pub eps: BExp,
+    pub trans: Vec<(BExp, String, u64)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CertEntry {
+    Start(u64, u64),
+    State(CertState),
+    Exp(u64, Exp),
+    Pair(u64, u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    pub start: (u64, u64),
+    pub states: Vec<CertState>,
+    pub exps: Vec<(u64, Exp)>,
+    pub pairs: Vec<(u64, u64)>,
+}
+
+// Right-associated binary form of an n-ary operator with at least 2 operands.
+pub fn nary<T>(xs: Vec<T>, f: fn(Box<T>, Box<T>) -> T) -> Option<T> {
+    if xs.len() < 2 {
+        return None;
+    }
+    let mut xs = xs.into_iter().rev();
+    let last = xs.next().unwrap();
+    Some(xs.fold(last, |acc, x| f(Box::new(x), Box::new(acc))))
+}
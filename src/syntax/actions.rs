@@ -0,0 +1,51 @@
+use crate::hash::{HashMap, HashMapExt};
+
+// Interning table of action names. Ids are dense indices into names, so
+// distinct names never share an id and every id maps back to its name.
+#[derive(Debug, Clone, Default)]
+pub struct Actions {
+    ids: HashMap<String, u64>,
+    names: Vec<String>,
+}
+
+impl Actions {
+    pub fn new() -> Self {
+        Actions {
+            ids: HashMap::new(),
+            names: vec![],
+        }
+    }
+
+    pub fn intern(&mut self, s: String) -> u64 {
+        if let Some(a) = self.ids.get(&s) {
+            return *a;
+        }
+        let a = self.names.len() as u64;
+        self.names.push(s.clone());
+        self.ids.insert(s, a);
+        a
+    }
+
+    pub fn name(&self, a: u64) -> &str {
+        &self.names[a as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[test]
+fn test() {
+    let mut acts = Actions::new();
+    let p = acts.intern("p".to_string());
+    let q = acts.intern("q".to_string());
+    assert_ne!(p, q);
+    assert_eq!(acts.intern("p".to_string()), p);
+    assert_eq!(acts.name(q), "q");
+    assert_eq!(acts.len(), 2);
+}
@@ -0,0 +1,135 @@
+use crate::parsing::BExp;
+
+// Product of literals; a cover is a disjunction of cubes.
+pub type Cube = Vec<(String, bool)>;
+
+// Boolean functions over numbered variables, with what isop needs of them.
+pub trait Isop {
+    type F: Clone + PartialEq;
+    fn zero(&self) -> Self::F;
+    fn one(&self) -> Self::F;
+    // A variable that lower or upper depends on, neither being constant.
+    fn top(&self, lower: &Self::F, upper: &Self::F) -> usize;
+    // Cofactors of f where var holds and where it does not.
+    fn split(&mut self, f: &Self::F, var: usize) -> (Self::F, Self::F);
+    // Function that is high where var holds and low elsewhere.
+    fn join(&mut self, var: usize, high: &Self::F, low: &Self::F) -> Self::F;
+    fn and(&mut self, f: &Self::F, g: &Self::F) -> Self::F;
+    fn and_not(&mut self, f: &Self::F, g: &Self::F) -> Self::F;
+    fn or(&mut self, f: &Self::F, g: &Self::F) -> Self::F;
+    fn name(&self, var: usize) -> String;
+}
+
+// Irredundant sum of products c with lower <= c <= upper, by the
+// Minato-Morreale algorithm. Returns the cubes and their function.
+pub fn isop<I: Isop>(m: &mut I, lower: &I::F, upper: &I::F) -> (Vec<Cube>, I::F) {
+    if *lower == m.zero() {
+        return (vec![], m.zero());
+    } else if *upper == m.one() {
+        return (vec![vec![]], m.one());
+    }
+    let var = m.top(lower, upper);
+    let (l1, l0) = m.split(lower, var);
+    let (u1, u0) = m.split(upper, var);
+    // cubes that need the negative literal, then the positive one
+    let l = m.and_not(&l0, &u1);
+    let (c0, r0) = isop(m, &l, &u0);
+    let l = m.and_not(&l1, &u0);
+    let (c1, r1) = isop(m, &l, &u1);
+    // the rest is covered without var
+    let l0 = m.and_not(&l0, &r0);
+    let l1 = m.and_not(&l1, &r1);
+    let l = m.or(&l0, &l1);
+    let u = m.and(&u0, &u1);
+    let (cd, rd) = isop(m, &l, &u);
+    let r = m.join(var, &r1, &r0);
+    let r = m.or(&r, &rd);
+    let name = m.name(var);
+    let mut cubes = vec![];
+    for (cs, v) in [(c0, false), (c1, true)] {
+        for mut c in cs {
+            c.insert(0, (name.clone(), v));
+            cubes.push(c);
+        }
+    }
+    cubes.extend(cd);
+    (cubes, r)
+}
+
+// Factor a cover by repeatedly pulling out the literal shared by the most
+// cubes: x y + x z + w becomes x (y + z) + w.
+pub fn factor_cover(cubes: &[Cube]) -> BExp {
+    use BExp::*;
+    if cubes.is_empty() {
+        return Zero;
+    }
+    if cubes.iter().any(|c| c.is_empty()) {
+        return One;
+    }
+    if cubes.len() == 1 {
+        return conj(&cubes[0]);
+    }
+    // most frequent literal, ties broken by the first occurrence
+    let mut counts: Vec<(&(String, bool), usize)> = vec![];
+    for lit in cubes.iter().flatten() {
+        match counts.iter_mut().find(|(l, _)| *l == lit) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((lit, 1)),
+        }
+    }
+    let (lit, n) = counts.iter().rev().max_by_key(|(_, n)| *n).unwrap();
+    if *n == 1 {
+        let mut ops = cubes.iter().rev().map(conj);
+        let last = ops.next().unwrap();
+        return ops.fold(last, |acc, b| Or(Box::new(b), Box::new(acc)));
+    }
+    let (with, without): (Vec<&Cube>, Vec<&Cube>) = cubes.iter().partition(|c| c.contains(lit));
+    let rest: Vec<Cube> = with
+        .iter()
+        .map(|c| c.iter().filter(|l| l != lit).cloned().collect())
+        .collect();
+    let x = literal(lit);
+    let common = match factor_cover(&rest) {
+        One => x,
+        b => And(Box::new(x), Box::new(b)),
+    };
+    if without.is_empty() {
+        return common;
+    }
+    let without: Vec<Cube> = without.into_iter().cloned().collect();
+    Or(Box::new(common), Box::new(factor_cover(&without)))
+}
+
+fn literal((s, v): &(String, bool)) -> BExp {
+    let x = BExp::PBool(s.clone());
+    if *v { x } else { BExp::Not(Box::new(x)) }
+}
+
+fn conj(cube: &Cube) -> BExp {
+    let mut lits = cube.iter().rev().map(literal);
+    let last = lits.next().unwrap_or(BExp::One);
+    lits.fold(last, |acc, b| BExp::And(Box::new(b), Box::new(acc)))
+}
+
+#[test]
+fn test() {
+    use super::*;
+    let lit = |s: &str| (s.trim_start_matches('!').to_string(), !s.starts_with('!'));
+    let cover: Vec<Cube> = [vec!["b1", "b2"], vec!["b1", "!b3"], vec!["b4"]]
+        .iter()
+        .map(|c| c.iter().map(|s| lit(s)).collect())
+        .collect();
+    let b = factor_cover(&cover);
+    assert_eq!(b.to_string(), "(or (and b1 (or b2 (not b3))) b4)");
+    // the factored form denotes the same function as the cover
+    let mut gkat = PureBDDGkat::new();
+    let mut sop = gkat.mk_zero();
+    for c in &cover {
+        let atom = gkat.mk_atom(&Atom(c.clone()));
+        sop = gkat.mk_or(&sop, &atom);
+    }
+    let b = gkat.from_bexp(b);
+    assert!(gkat.is_equiv(&sop, &b));
+    assert_eq!(factor_cover(&[]).to_string(), "0");
+    assert_eq!(factor_cover(&[vec![]]).to_string(), "1");
+}
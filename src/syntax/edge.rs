@@ -0,0 +1,16 @@
+// Edges of the graph-based backends: the node index shifted left, with the
+// low bit set when the edge complements the function of the node.
+pub type Edge = u32;
+
+pub const ONE: Edge = 0;
+pub const ZERO: Edge = 1;
+
+#[inline]
+pub fn index(e: Edge) -> usize {
+    (e >> 1) as usize
+}
+
+#[inline]
+pub fn is_complement(e: Edge) -> bool {
+    e & 1 == 1
+}
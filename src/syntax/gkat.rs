@@ -1,10 +1,7 @@
+use super::*;
 use crate::parsing::{self};
-use gxhash::GxHasher;
 use hashconsing::HConsed;
-use std::{
-    fmt::Debug,
-    hash::{Hash, Hasher},
-};
+use std::{fmt::Debug, hash::Hash};
 
 // Trait for generic BExp.
 pub trait BExp: Debug + Clone + Hash + Eq {}
@@ -14,6 +11,7 @@ pub type Exp<B> = HConsed<Exp_<B>>;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum Exp_<B> {
+    // id interned in the Actions table of the manager
     Act(u64),
     Seq(Exp<B>, Exp<B>),
     Ifte(B, Exp<B>, Exp<B>),
@@ -30,10 +28,33 @@ pub trait Gkat<B: Clone + Hash + Eq> {
     fn mk_and(&mut self, b1: &B, b2: &B) -> B;
     fn mk_or(&mut self, b1: &B, b2: &B) -> B;
     fn mk_not(&mut self, b: &B) -> B;
+    // Restrict the atoms considered by is_false, is_equiv and pick_atom.
+    fn add_constraint(&mut self, b: &B);
     fn is_false(&mut self, b: &B) -> bool;
     fn is_equiv(&mut self, b1: &B, b2: &B) -> bool;
+    fn pick_atom(&mut self, b: &B) -> Option<Atom>;
+    fn to_bexp(&mut self, b: &B) -> parsing::BExp;
+    // Start a fresh budget for the backend. Once it runs out, exhausted
+    // reports why and every test counts as false.
+    fn set_limits(&mut self, limits: &Limits);
+    fn exhausted(&self) -> Option<Limit>;
+    // Reorder variables dynamically; a no-op for backends without an order.
+    fn set_reordering(&mut self, method: Reorder);
+    fn stats(&self) -> GkatStats;
+
+    // Create the BExp satisfied by exactly one atom.
+    fn mk_atom(&mut self, atom: &Atom) -> B {
+        let mut acc = self.mk_one();
+        for (s, v) in &atom.0 {
+            let x = self.mk_var(s.clone());
+            let lit = if *v { x } else { self.mk_not(&x) };
+            acc = self.mk_and(&acc, &lit);
+        }
+        acc
+    }
 
     // Create a new BExp from parsing.
+    #[allow(clippy::wrong_self_convention)]
     fn from_bexp(&mut self, raw: parsing::BExp) -> B {
         use parsing::BExp::*;
         match raw {
@@ -60,10 +81,14 @@ pub trait Gkat<B: Clone + Hash + Eq> {
     // Methods for program expressions.
     fn hashcons(&mut self, e: Exp_<B>) -> Exp<B>;
 
+    fn actions(&mut self) -> &mut Actions;
+
+    fn act_name(&mut self, a: u64) -> String {
+        self.actions().name(a).to_string()
+    }
+
     fn mk_act(&mut self, s: String) -> Exp<B> {
-        let mut hasher = GxHasher::default();
-        s.hash(&mut hasher);
-        let a = hasher.finish();
+        let a = self.actions().intern(s);
         self.hashcons(Exp_::Act(a))
     }
 
@@ -81,7 +106,7 @@ pub trait Gkat<B: Clone + Hash + Eq> {
         use Exp_::*;
         match (p1.get(), p2.get()) {
             (Test(b1), Test(b2)) => {
-                let b3 = self.mk_and(&b1, &b2);
+                let b3 = self.mk_and(b1, b2);
                 self.mk_test(b3)
             }
             (Test(b), _) if b == &self.mk_zero() => self.mk_fail(),
@@ -111,6 +136,7 @@ pub trait Gkat<B: Clone + Hash + Eq> {
     }
 
     // Create a new Exp from parsing.
+    #[allow(clippy::wrong_self_convention)]
     fn from_exp(&mut self, raw: parsing::Exp) -> Exp<B> {
         use parsing::Exp::*;
         match raw {
@@ -137,4 +163,35 @@ pub trait Gkat<B: Clone + Hash + Eq> {
             }
         }
     }
+
+    // Print an Exp with its guards converted back into formulas.
+    fn print_exp(&mut self, exp: &Exp<B>, syntax: parsing::Syntax, indent: bool) -> String {
+        let exp = self.to_exp(exp);
+        parsing::print_exp(&exp, syntax, indent)
+    }
+
+    // Convert an Exp back into its parsing form.
+    fn to_exp(&mut self, exp: &Exp<B>) -> parsing::Exp {
+        use parsing::Exp::*;
+        match exp.get() {
+            Exp_::Act(a) => Act(self.act_name(*a)),
+            Exp_::Seq(p1, p2) => {
+                let p1 = self.to_exp(p1);
+                let p2 = self.to_exp(p2);
+                Seq(Box::new(p1), Box::new(p2))
+            }
+            Exp_::Ifte(b, p1, p2) => {
+                let b = self.to_bexp(b);
+                let p1 = self.to_exp(p1);
+                let p2 = self.to_exp(p2);
+                Ifte(b, Box::new(p1), Box::new(p2))
+            }
+            Exp_::Test(b) => Test(self.to_bexp(b)),
+            Exp_::While(b, p) => {
+                let b = self.to_bexp(b);
+                let p = self.to_exp(p);
+                While(b, Box::new(p))
+            }
+        }
+    }
 }
@@ -0,0 +1,502 @@
+use super::edge::*;
+use super::tseitin::Tseitin;
+use super::*;
+use crate::hash::{BuildHasher, HashMap};
+use crate::parsing;
+use hashconsing::{HConsign, HashConsign};
+use logicng::solver::minisat::sat::{MsLit, Tristate, not};
+use std::{hash::BuildHasher as _, time::Instant};
+
+// First fanin of the constant and of the inputs, whose second fanin is
+// the variable index.
+const LEAF: Edge = u32::MAX;
+
+// Words of simulation patterns per node at the start and at most;
+// counterexamples of failed merges add a word at a time.
+const SIM_WORDS: usize = 4;
+const MAX_SIM_WORDS: usize = 16;
+
+// Candidates of a simulation class tried before a node is kept as is.
+const MAX_CANDIDATES: usize = 8;
+
+// BExp based on the AIG: an edge into the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AIGBExp(Edge);
+
+impl BExp for AIGBExp {}
+
+// Literal equivalent to e, adding the clauses of the nodes not encoded yet.
+fn encode(cnf: &mut Tseitin<u32>, nodes: &[(Edge, Edge)], e: Edge) -> MsLit {
+    let n = index(e) as u32;
+    let x = match cnf.get(&n) {
+        Some(x) => x,
+        None => {
+            let x = match nodes[n as usize] {
+                (LEAF, _) if n == 0 => cnf.one(),
+                (LEAF, _) => cnf.input(),
+                (f0, f1) => {
+                    let ops = [encode(cnf, nodes, f0), encode(cnf, nodes, f1)];
+                    cnf.and(&ops)
+                }
+            };
+            cnf.insert(n, x);
+            x
+        }
+    };
+    if is_complement(e) { not(x) } else { x }
+}
+
+// Gkat based on an and-inverter graph. AND nodes are structurally hashed,
+// and every new node is simulated on random patterns; when its patterns
+// match those of an older node, a SAT call decides whether the two are
+// equivalent, and if so the new node is merged into the old one. Since
+// the constant is an older node too, unsatisfiable nodes become ZERO.
+pub struct AIGGkat {
+    name_map: HashMap<String, AIGBExp>,
+    // names and input nodes by variable index
+    var_names: Vec<String>,
+    inputs: Vec<u32>,
+    // fanins of every node
+    nodes: Vec<(Edge, Edge)>,
+    strash: HashMap<(Edge, Edge), Edge>,
+    // simulation patterns, words per node, by node
+    sims: Vec<u64>,
+    words: usize,
+    // nodes by the hash of their patterns, normalized so that the first
+    // pattern is 0; the hasher keeps its seed for the hashes to agree
+    classes: HashMap<u64, Vec<u32>>,
+    hasher: BuildHasher,
+    // counterexamples not simulated yet, as input values
+    cexs: Vec<Vec<bool>>,
+    rng: u64,
+    cnf: Tseitin<u32>,
+    exp_hcons: HConsign<Exp_<AIGBExp>, BuildHasher>,
+    actions: Actions,
+    // atoms outside the constraint are impossible
+    constraint: AIGBExp,
+    // resource limits
+    deadline: Option<Instant>,
+    max_conflicts: Option<usize>,
+    exhausted: Option<Limit>,
+    stats: GkatStats,
+}
+
+impl AIGGkat {
+    pub fn new() -> Self {
+        let mut gkat = Self {
+            name_map: HashMap::default(),
+            var_names: vec![],
+            inputs: vec![],
+            nodes: vec![(LEAF, 0)],
+            strash: HashMap::default(),
+            sims: vec![!0; SIM_WORDS],
+            words: SIM_WORDS,
+            classes: HashMap::default(),
+            hasher: BuildHasher::default(),
+            cexs: vec![],
+            rng: 0x2545_f491_4f6c_dd1d,
+            cnf: Tseitin::new(),
+            exp_hcons: HConsign::with_hasher(BuildHasher::default()),
+            actions: Actions::new(),
+            constraint: AIGBExp(ONE),
+            deadline: None,
+            max_conflicts: None,
+            exhausted: None,
+            stats: GkatStats::default(),
+        };
+        let key = gkat.class_key(0);
+        gkat.classes.insert(key, vec![0]);
+        gkat
+    }
+
+    // xorshift64
+    fn random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    fn sim(&self, n: u32) -> &[u64] {
+        &self.sims[n as usize * self.words..(n as usize + 1) * self.words]
+    }
+
+    // Word k of the patterns of e.
+    #[inline]
+    fn word(&self, e: Edge, k: usize) -> u64 {
+        let w = self.sims[index(e) * self.words + k];
+        if is_complement(e) { !w } else { w }
+    }
+
+    fn class_key(&self, n: u32) -> u64 {
+        let sim = self.sim(n);
+        let flip = if sim[0] & 1 == 1 { !0 } else { 0 };
+        self.hasher
+            .hash_one(sim.iter().map(|w| w ^ flip).collect::<Vec<_>>())
+    }
+
+    // Edge to an older node equivalent to n, which is the newest node.
+    fn sweep(&mut self, n: u32) -> Option<Edge> {
+        let candidates = self.classes.get(&self.class_key(n)).cloned();
+        let candidates = candidates.unwrap_or_default();
+        for r in candidates.into_iter().take(MAX_CANDIDATES) {
+            // equal patterns up to complement
+            let phase = (self.sim(n)[0] ^ self.sim(r)[0]) & 1;
+            let e = r << 1 | phase as u32;
+            if (0..self.words).any(|k| self.word(n << 1, k) != self.word(e, k)) {
+                continue;
+            }
+            // n differs from e when one holds without the other
+            let miter = [[n << 1, e ^ 1], [n << 1 | 1, e]];
+            let mut equal = true;
+            for lits in miter {
+                match self.solve(lits) {
+                    Tristate::False => {}
+                    Tristate::True => {
+                        equal = false;
+                        break;
+                    }
+                    Tristate::Undef => return None,
+                }
+            }
+            if equal {
+                self.stats.merges += 1;
+                return Some(e);
+            }
+        }
+        // counterexamples may have added patterns since
+        let key = self.class_key(n);
+        self.classes.entry(key).or_default().push(n);
+        None
+    }
+
+    // Decide whether the edges hold together, within the budget. Sets
+    // exhausted when the budget runs out and records counterexamples.
+    fn solve(&mut self, edges: [Edge; 2]) -> Tristate {
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.exhausted = Some(Limit::Timeout);
+            return Tristate::Undef;
+        }
+        self.stats.sat_calls += 1;
+        let result = match self.max_conflicts {
+            None => {
+                self.cnf.restart();
+                let lits = edges.map(|e| encode(&mut self.cnf, &self.nodes, e));
+                let result = self.cnf.solve(lits.to_vec());
+                if result == Tristate::True {
+                    self.counterexample();
+                }
+                result
+            }
+            Some(n) => {
+                let mut cnf = Tseitin::new();
+                let lits = edges.map(|e| encode(&mut cnf, &self.nodes, e));
+                cnf.search(lits.to_vec(), n)
+            }
+        };
+        if result == Tristate::Undef {
+            self.exhausted = Some(Limit::Conflicts);
+        }
+        result
+    }
+
+    // Input values of the model of the last call.
+    fn model(&self) -> Vec<bool> {
+        self.inputs.iter().map(|n| self.cnf.value(n)).collect()
+    }
+
+    // Keep the model of the last call as a simulation pattern, so that the
+    // nodes it told apart stop being candidates.
+    fn counterexample(&mut self) {
+        if self.words == MAX_SIM_WORDS {
+            return;
+        }
+        self.cexs.push(self.model());
+        if self.cexs.len() == 64 {
+            self.resimulate();
+        }
+    }
+
+    // Add a word holding the counterexamples to every node.
+    fn resimulate(&mut self) {
+        let cexs = std::mem::take(&mut self.cexs);
+        let mut inputs = vec![0; self.nodes.len()];
+        for (i, n) in self.inputs.iter().enumerate() {
+            inputs[*n as usize] = (cexs.iter().enumerate()).fold(0, |w, (j, cex)| {
+                w | (cex.get(i).copied().unwrap_or(false) as u64) << j
+            });
+        }
+        let (old, words) = (std::mem::take(&mut self.sims), self.words);
+        self.words += 1;
+        self.sims = Vec::with_capacity(self.nodes.len() * self.words);
+        for n in 0..self.nodes.len() {
+            self.sims
+                .extend_from_slice(&old[n * words..(n + 1) * words]);
+            let w = match self.nodes[n] {
+                (LEAF, _) if n == 0 => !0,
+                (LEAF, _) => inputs[n],
+                (f0, f1) => self.word(f0, words) & self.word(f1, words),
+            };
+            self.sims.push(w);
+        }
+        // nodes left are pairwise inequivalent, so each keeps to itself
+        // unless the new patterns still agree
+        let mut classes: HashMap<u64, Vec<u32>> = HashMap::default();
+        for n in self
+            .classes
+            .drain()
+            .flat_map(|(_, ns)| ns)
+            .collect::<Vec<_>>()
+        {
+            classes.entry(self.class_key(n)).or_default().push(n);
+        }
+        for ns in classes.values_mut() {
+            ns.sort();
+        }
+        self.classes = classes;
+    }
+
+    fn and(&mut self, e1: Edge, e2: Edge) -> Edge {
+        if e1 == ZERO || e2 == ZERO || e1 == e2 ^ 1 {
+            return ZERO;
+        } else if e1 == ONE || e1 == e2 {
+            return e2;
+        } else if e2 == ONE {
+            return e1;
+        }
+        let key = (e1.min(e2), e1.max(e2));
+        if let Some(e) = self.strash.get(&key) {
+            return *e;
+        }
+        let n = self.nodes.len() as u32;
+        self.nodes.push(key);
+        for k in 0..self.words {
+            let w = self.word(key.0, k) & self.word(key.1, k);
+            self.sims.push(w);
+        }
+        // without a budget to sweep n, it stays out of the tables, so that
+        // the same AND made later gets swept then
+        if self.exhausted.is_some() {
+            return n << 1;
+        }
+        let e = match self.sweep(n) {
+            Some(e) => {
+                // nothing points to n yet
+                self.nodes.pop();
+                self.sims.truncate(self.nodes.len() * self.words);
+                self.cnf.remove(&n);
+                e
+            }
+            // the budget ran out before n was compared to every candidate
+            None if self.exhausted.is_some() => return n << 1,
+            None => n << 1,
+        };
+        self.strash.insert(key, e);
+        e
+    }
+
+    fn to_bexp_edge(&self, e: Edge) -> parsing::BExp {
+        use parsing::BExp::*;
+        let not = |b| Not(Box::new(b));
+        match (e, self.nodes[index(e)]) {
+            (ONE, _) => One,
+            (ZERO, _) => Zero,
+            (_, (LEAF, i)) => {
+                let x = PBool(self.var_names[i as usize].clone());
+                if is_complement(e) { not(x) } else { x }
+            }
+            // not (not x and not y) reads better as x or y
+            (_, (f0, f1)) if is_complement(e) && is_complement(f0) && is_complement(f1) => Or(
+                Box::new(self.to_bexp_edge(f0 ^ 1)),
+                Box::new(self.to_bexp_edge(f1 ^ 1)),
+            ),
+            (_, (f0, f1)) => {
+                let b = And(
+                    Box::new(self.to_bexp_edge(f0)),
+                    Box::new(self.to_bexp_edge(f1)),
+                );
+                if is_complement(e) { not(b) } else { b }
+            }
+        }
+    }
+}
+
+impl Default for AIGGkat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gkat<AIGBExp> for AIGGkat {
+    #[inline]
+    fn mk_zero(&mut self) -> AIGBExp {
+        AIGBExp(ZERO)
+    }
+
+    #[inline]
+    fn mk_one(&mut self) -> AIGBExp {
+        AIGBExp(ONE)
+    }
+
+    fn mk_var(&mut self, s: String) -> AIGBExp {
+        if let Some(x) = self.name_map.get(&s) {
+            return *x;
+        }
+        let n = self.nodes.len() as u32;
+        self.nodes.push((LEAF, self.var_names.len() as u32));
+        for _ in 0..self.words {
+            let w = self.random();
+            self.sims.push(w);
+        }
+        let key = self.class_key(n);
+        self.classes.entry(key).or_default().push(n);
+        self.inputs.push(n);
+        self.var_names.push(s.clone());
+        let x = AIGBExp(n << 1);
+        self.name_map.insert(s, x);
+        x
+    }
+
+    fn mk_and(&mut self, b1: &AIGBExp, b2: &AIGBExp) -> AIGBExp {
+        AIGBExp(self.and(b1.0, b2.0))
+    }
+
+    fn mk_or(&mut self, b1: &AIGBExp, b2: &AIGBExp) -> AIGBExp {
+        AIGBExp(self.and(b1.0 ^ 1, b2.0 ^ 1) ^ 1)
+    }
+
+    #[inline]
+    fn mk_not(&mut self, b: &AIGBExp) -> AIGBExp {
+        AIGBExp(b.0 ^ 1)
+    }
+
+    fn add_constraint(&mut self, b: &AIGBExp) {
+        self.constraint = self.mk_and(&self.constraint.clone(), b);
+    }
+
+    fn is_false(&mut self, b: &AIGBExp) -> bool {
+        self.stats.is_false_calls += 1;
+        // merging leaves only satisfiable nodes apart from ZERO
+        let b = self.and(b.0, self.constraint.0);
+        self.exhausted.is_some() || b == ZERO
+    }
+
+    fn is_equiv(&mut self, b1: &AIGBExp, b2: &AIGBExp) -> bool {
+        self.stats.is_equiv_calls += 1;
+        if b1 == b2 {
+            return true;
+        }
+        // the edges differ where exactly one of them holds
+        let (e1, e2) = (b1.0, b2.0);
+        let only1 = self.and(e1, e2 ^ 1);
+        let only2 = self.and(e1 ^ 1, e2);
+        let diff = self.and(only1 ^ 1, only2 ^ 1) ^ 1;
+        let diff = self.and(diff, self.constraint.0);
+        self.exhausted.is_some() || diff == ZERO
+    }
+
+    fn pick_atom(&mut self, b: &AIGBExp) -> Option<Atom> {
+        let b = self.and(b.0, self.constraint.0);
+        if self.exhausted.is_some() || b == ZERO {
+            return None;
+        }
+        // a pattern that satisfies b, or else a model
+        let pattern = (0..self.words).find_map(|k| {
+            let w = self.word(b, k);
+            (w != 0).then(|| k * 64 + w.trailing_zeros() as usize)
+        });
+        let values: Vec<bool> = match pattern {
+            Some(j) => (self.inputs.iter())
+                .map(|n| self.word(n << 1, j / 64) >> (j % 64) & 1 == 1)
+                .collect(),
+            None => {
+                self.stats.sat_calls += 1;
+                self.cnf.restart();
+                let x = encode(&mut self.cnf, &self.nodes, b);
+                let result = self.cnf.solve(vec![x]);
+                assert!(result == Tristate::True);
+                self.model()
+            }
+        };
+        let mut atom: Vec<_> = self.var_names.iter().cloned().zip(values).collect();
+        atom.sort();
+        Some(Atom(atom))
+    }
+
+    fn to_bexp(&mut self, b: &AIGBExp) -> parsing::BExp {
+        self.to_bexp_edge(b.0)
+    }
+
+    fn set_limits(&mut self, limits: &Limits) {
+        self.deadline = limits.deadline();
+        self.max_conflicts = limits.max_conflicts;
+        self.exhausted = None;
+    }
+
+    #[inline]
+    fn exhausted(&self) -> Option<Limit> {
+        self.exhausted
+    }
+
+    fn set_reordering(&mut self, _: Reorder) {}
+
+    fn stats(&self) -> GkatStats {
+        GkatStats {
+            nodes: self.nodes.len(),
+            peak_nodes: self.nodes.len(),
+            ..self.stats.clone()
+        }
+    }
+
+    #[inline]
+    fn hashcons(&mut self, e: Exp_<AIGBExp>) -> Exp<AIGBExp> {
+        self.exp_hcons.mk(e)
+    }
+
+    fn actions(&mut self) -> &mut Actions {
+        &mut self.actions
+    }
+}
+
+#[test]
+fn test() {
+    use super::testing::parse_bexp;
+    let mut gkat = AIGGkat::new();
+    // equivalent nodes are merged, so equivalence is equality
+    let b1 = gkat.from_bexp(parse_bexp("(or (and a b) (and a (not b)))"));
+    let b2 = gkat.mk_var("a".to_string());
+    assert_eq!(b1, b2);
+    let b3 = gkat.from_bexp(parse_bexp("(and (and a (or (not a) b)) (not b))"));
+    assert_eq!(b3, gkat.mk_zero());
+    assert!(gkat.stats().merges >= 2);
+    let b = gkat.from_bexp(parse_bexp("(or (and a b) (and (not a) c))"));
+    let e = gkat.to_bexp(&b);
+    assert_eq!(gkat.from_bexp(e), b);
+    let atom = gkat.pick_atom(&b).unwrap();
+    let a = gkat.mk_atom(&atom);
+    let ab = gkat.mk_and(&a, &b);
+    assert!(!gkat.is_false(&ab));
+    let k = gkat.mk_not(&b2);
+    gkat.add_constraint(&k);
+    assert!(gkat.is_false(&b2));
+    let c = gkat.mk_var("c".to_string());
+    assert!(gkat.is_equiv(&b, &c));
+    assert!(
+        gkat.pick_atom(&b)
+            .unwrap()
+            .0
+            .contains(&("a".to_string(), false))
+    );
+    // a node left unswept by an exhausted budget is swept when made again
+    let mut gkat = AIGGkat::new();
+    let a = gkat.mk_var("a".to_string());
+    gkat.set_limits(&Limits {
+        timeout: Some(std::time::Duration::ZERO),
+        ..Limits::default()
+    });
+    let b = parse_bexp("(or (and a b) (and a (not b)))");
+    assert_ne!(gkat.from_bexp(b.clone()), a);
+    assert_eq!(gkat.exhausted(), Some(Limit::Timeout));
+    gkat.set_limits(&Limits::default());
+    assert_eq!(gkat.from_bexp(b), a);
+}
@@ -1,10 +1,11 @@
 use super::*;
+use crate::hash::{BuildHasher, HashMap};
+use crate::parsing;
 use core::fmt;
 use cudd::*;
 use cudd_sys::*;
-use gxhash::{GxBuildHasher, HashMap};
 use hashconsing::{HConsign, HashConsign};
-use std::{fmt::Debug, hash::Hash, ptr};
+use std::{fmt::Debug, hash::Hash, os::raw::c_int, ptr, slice};
 
 // BExp based on BDD.
 pub struct BDDBExp {
@@ -31,9 +32,9 @@ impl Clone for BDDBExp {
 }
 
 impl Debug for BDDBExp {
-    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> std::fmt::Result {
-        unsafe { Cudd_PrintMinterm(self.cudd, self.node) };
-        std::fmt::Result::Ok(())
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        // variable names live in the BDDGkat, see to_bexp
+        write!(f, "BDDBExp({:p})", self.node)
     }
 }
 
@@ -73,7 +74,15 @@ impl BDDManager {
 
 pub struct BDDGkat {
     name_map: HashMap<String, BDDBExp>,
-    exp_hcons: HConsign<Exp_<BDDBExp>, GxBuildHasher>,
+    // names by CUDD variable index
+    var_names: Vec<String>,
+    exp_hcons: HConsign<Exp_<BDDBExp>, BuildHasher>,
+    actions: Actions,
+    // atoms outside the constraint are impossible
+    constraint: Option<BDDBExp>,
+    // resource limits
+    exhausted: Option<Limit>,
+    stats: GkatStats,
     // BDD manager
     man: BDDManager,
 }
@@ -82,12 +91,100 @@ impl BDDGkat {
     pub fn new() -> Self {
         Self {
             name_map: HashMap::default(),
-            exp_hcons: HConsign::with_hasher(GxBuildHasher::default()),
+            var_names: vec![],
+            exp_hcons: HConsign::with_hasher(BuildHasher::default()),
+            actions: Actions::new(),
+            constraint: None,
+            exhausted: None,
+            stats: GkatStats::default(),
             man: BDDManager::new(),
         }
     }
 }
 
+impl BDDGkat {
+    // Wrap the result of a CUDD operation, which is null once a limit
+    // is hit; the budget is then exhausted and the result is false.
+    fn wrap(&mut self, node: *mut DdNode) -> BDDBExp {
+        if node.is_null() {
+            let limit = match unsafe { Cudd_ReadErrorCode(self.man.0) } {
+                Cudd_ErrorType::CUDD_TIMEOUT_EXPIRED => Limit::Timeout,
+                _ => Limit::Nodes,
+            };
+            self.exhausted.get_or_insert(limit);
+            return self.mk_zero();
+        }
+        unsafe { Cudd_Ref(node) };
+        BDDBExp {
+            cudd: self.man.0,
+            node: node,
+        }
+    }
+
+    // Shannon expansion of a BDD node into a formula.
+    // Prime irredundant cover of node, None if CUDD runs out of memory.
+    fn prime_cover(&self, node: *mut DdNode) -> Option<Vec<Cube>> {
+        let mut cubes = vec![];
+        unsafe {
+            let mut cube: *mut c_int = ptr::null_mut();
+            let generator = Cudd_FirstPrime(self.man.0, node, node, &mut cube);
+            if generator.is_null() {
+                return None;
+            }
+            while Cudd_IsGenEmpty(generator) == 0 {
+                let values = slice::from_raw_parts(cube, self.var_names.len());
+                let lits = values.iter().enumerate().filter(|(_, v)| **v != 2);
+                cubes.push(
+                    lits.map(|(i, v)| (self.var_names[i].clone(), *v == 1))
+                        .collect(),
+                );
+                Cudd_NextPrime(generator, &mut cube);
+            }
+            Cudd_GenFree(generator);
+        }
+        Some(cubes)
+    }
+
+    // Shannon expansion of node.
+    fn node_to_bexp(&self, node: *mut DdNode) -> parsing::BExp {
+        use parsing::BExp::*;
+        unsafe {
+            let one = Cudd_ReadOne(self.man.0);
+            let zero = Cudd_ReadLogicZero(self.man.0);
+            if node == one {
+                return One;
+            } else if node == zero {
+                return Zero;
+            }
+            let r = Cudd_Regular(node);
+            let x = PBool(self.var_names[Cudd_NodeReadIndex(r) as usize].clone());
+            let (t, e) = if Cudd_IsComplement(node) != 0 {
+                (Cudd_Not(Cudd_T(r)), Cudd_Not(Cudd_E(r)))
+            } else {
+                (Cudd_T(r), Cudd_E(r))
+            };
+            let nx = Not(Box::new(x.clone()));
+            if t == one && e == zero {
+                x
+            } else if t == zero && e == one {
+                nx
+            } else if t == one {
+                Or(Box::new(x), Box::new(self.node_to_bexp(e)))
+            } else if t == zero {
+                And(Box::new(nx), Box::new(self.node_to_bexp(e)))
+            } else if e == one {
+                Or(Box::new(nx), Box::new(self.node_to_bexp(t)))
+            } else if e == zero {
+                And(Box::new(x), Box::new(self.node_to_bexp(t)))
+            } else {
+                let xt = And(Box::new(x), Box::new(self.node_to_bexp(t)));
+                let nxe = And(Box::new(nx), Box::new(self.node_to_bexp(e)));
+                Or(Box::new(xt), Box::new(nxe))
+            }
+        }
+    }
+}
+
 impl Gkat<BDDBExp> for BDDGkat {
     fn mk_zero(&mut self) -> BDDBExp {
         unsafe {
@@ -115,38 +212,26 @@ impl Gkat<BDDBExp> for BDDGkat {
         if let Some(x) = self.name_map.get(&s) {
             return x.clone();
         }
-        let x = unsafe {
-            let node = Cudd_bddNewVar(self.man.0);
-            Cudd_Ref(node);
-            BDDBExp {
-                cudd: self.man.0,
-                node: node,
-            }
-        };
+        let node = unsafe { Cudd_bddNewVar(self.man.0) };
+        if !node.is_null() {
+            self.var_names.push(s.clone());
+        }
+        let x = self.wrap(node);
+        if self.exhausted.is_some() {
+            return x;
+        }
         self.name_map.insert(s, x.clone());
         return x;
     }
 
     fn mk_and(&mut self, b1: &BDDBExp, b2: &BDDBExp) -> BDDBExp {
-        unsafe {
-            let node = Cudd_bddAnd(self.man.0, b1.node, b2.node);
-            Cudd_Ref(node);
-            BDDBExp {
-                cudd: self.man.0,
-                node: node,
-            }
-        }
+        let node = unsafe { Cudd_bddAnd(self.man.0, b1.node, b2.node) };
+        self.wrap(node)
     }
 
     fn mk_or(&mut self, b1: &BDDBExp, b2: &BDDBExp) -> BDDBExp {
-        unsafe {
-            let node = Cudd_bddOr(self.man.0, b1.node, b2.node);
-            Cudd_Ref(node);
-            BDDBExp {
-                cudd: self.man.0,
-                node: node,
-            }
-        }
+        let node = unsafe { Cudd_bddOr(self.man.0, b1.node, b2.node) };
+        self.wrap(node)
     }
 
     fn mk_not(&mut self, b: &BDDBExp) -> BDDBExp {
@@ -160,18 +245,129 @@ impl Gkat<BDDBExp> for BDDGkat {
         }
     }
 
+    fn add_constraint(&mut self, b: &BDDBExp) {
+        let k = match self.constraint.clone() {
+            Some(k) => self.mk_and(&k, b),
+            None => b.clone(),
+        };
+        self.constraint = Some(k);
+    }
+
     #[inline]
     fn is_false(&mut self, b: &BDDBExp) -> bool {
-        unsafe { b.node == Cudd_ReadLogicZero(self.man.0) }
+        self.stats.is_false_calls += 1;
+        if self.exhausted.is_some() {
+            return true;
+        }
+        match &self.constraint {
+            // b implies the negated constraint
+            Some(k) => unsafe { Cudd_bddLeq(self.man.0, b.node, Cudd_Not(k.node)) != 0 },
+            None => unsafe { b.node == Cudd_ReadLogicZero(self.man.0) },
+        }
     }
 
     #[inline]
     fn is_equiv(&mut self, b1: &BDDBExp, b2: &BDDBExp) -> bool {
-        b1 == b2
+        self.stats.is_equiv_calls += 1;
+        if self.exhausted.is_some() {
+            return true;
+        }
+        match &self.constraint {
+            // b1 and b2 agree wherever the constraint holds
+            Some(k) => unsafe { Cudd_EquivDC(self.man.0, b1.node, b2.node, Cudd_Not(k.node)) != 0 },
+            None => b1 == b2,
+        }
+    }
+
+    fn pick_atom(&mut self, b: &BDDBExp) -> Option<Atom> {
+        if self.exhausted.is_some() {
+            return None;
+        }
+        let b = match self.constraint.clone() {
+            Some(k) => self.mk_and(b, &k),
+            None => b.clone(),
+        };
+        let size = unsafe { Cudd_ReadSize(self.man.0) } as usize;
+        let mut cube = vec![0 as std::os::raw::c_char; size.max(1)];
+        if unsafe { Cudd_bddPickOneCube(self.man.0, b.node, cube.as_mut_ptr()) } == 0 {
+            return None;
+        }
+        let mut atom: Vec<_> = self
+            .name_map
+            .iter()
+            .map(|(s, x)| {
+                let i = unsafe { Cudd_NodeReadIndex(x.node) } as usize;
+                // don't-care positions (2) are resolved to false
+                (s.clone(), cube[i] == 1)
+            })
+            .collect();
+        atom.sort();
+        Some(Atom(atom))
+    }
+
+    fn to_bexp(&mut self, b: &BDDBExp) -> parsing::BExp {
+        match self.prime_cover(b.node) {
+            Some(cubes) => factor_cover(&cubes),
+            None => self.node_to_bexp(b.node),
+        }
+    }
+
+    fn set_limits(&mut self, limits: &Limits) {
+        let max_live = limits
+            .max_nodes
+            .map_or(u32::MAX, |n| n.min(u32::MAX as usize) as u32);
+        unsafe {
+            Cudd_ClearErrorCode(self.man.0);
+            Cudd_SetMaxLive(self.man.0, max_live);
+            Cudd_UnsetTimeLimit(self.man.0);
+            if let Some(t) = limits.timeout {
+                Cudd_ResetStartTime(self.man.0);
+                Cudd_SetTimeLimit(self.man.0, t.as_millis() as std::os::raw::c_ulong);
+            }
+        }
+        self.exhausted = None;
+    }
+
+    #[inline]
+    fn exhausted(&self) -> Option<Limit> {
+        self.exhausted
+    }
+
+    fn set_reordering(&mut self, method: Reorder) {
+        use Cudd_ReorderingType::*;
+        let method = match method {
+            Reorder::None => {
+                unsafe { Cudd_AutodynDisable(self.man.0) };
+                return;
+            }
+            Reorder::Sift => CUDD_REORDER_SIFT,
+            Reorder::SiftConverge => CUDD_REORDER_SIFT_CONVERGE,
+            Reorder::SymmSift => CUDD_REORDER_SYMM_SIFT,
+            Reorder::GroupSift => CUDD_REORDER_GROUP_SIFT,
+            Reorder::Window => CUDD_REORDER_WINDOW3_CONV,
+            Reorder::Annealing => CUDD_REORDER_ANNEALING,
+            Reorder::Genetic => CUDD_REORDER_GENETIC,
+            Reorder::Exact => CUDD_REORDER_EXACT,
+        };
+        unsafe { Cudd_AutodynEnable(self.man.0, method) };
+    }
+
+    fn stats(&self) -> GkatStats {
+        GkatStats {
+            nodes: unsafe { Cudd_ReadNodeCount(self.man.0) } as usize,
+            peak_nodes: unsafe { Cudd_ReadPeakLiveNodeCount(self.man.0) } as usize,
+            reorderings: unsafe { Cudd_ReadReorderings(self.man.0) } as usize,
+            reorder_time: unsafe { Cudd_ReadReorderingTime(self.man.0) } as usize,
+            ..self.stats.clone()
+        }
     }
 
     #[inline]
     fn hashcons(&mut self, e: Exp_<BDDBExp>) -> Exp<BDDBExp> {
         self.exp_hcons.mk(e)
     }
+
+    fn actions(&mut self) -> &mut Actions {
+        &mut self.actions
+    }
 }
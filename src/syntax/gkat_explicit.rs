@@ -0,0 +1,369 @@
+use super::*;
+use crate::hash::{BuildHasher, HashMap};
+use crate::parsing;
+use hashconsing::{HConsign, HashConsign};
+use std::rc::Rc;
+
+// Truth table of atom bit i within one word, for the variables below 6.
+const PATTERNS: [u64; 6] = [
+    0xaaaa_aaaa_aaaa_aaaa,
+    0xcccc_cccc_cccc_cccc,
+    0xf0f0_f0f0_f0f0_f0f0,
+    0xff00_ff00_ff00_ff00,
+    0xffff_0000_ffff_0000,
+    0xffff_ffff_0000_0000,
+];
+
+// BExp of ExplicitGkat: an interned truth table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExplicitBExp(u32);
+
+impl BExp for ExplicitBExp {}
+
+const ZERO: ExplicitBExp = ExplicitBExp(0);
+const ONE: ExplicitBExp = ExplicitBExp(1);
+
+// Gkat over a fixed number of test variables, representing every BExp by
+// the set of atoms satisfying it: bit a of the table is set when atom a,
+// whose bit i is the value of variable i, satisfies the BExp. Meant for
+// few variables, where connectives and tests become word operations.
+pub struct ExplicitGkat {
+    name_map: HashMap<String, ExplicitBExp>,
+    // names by variable index
+    var_names: Vec<String>,
+    // variables the tables have room for
+    capacity: usize,
+    // atoms in use in the last word
+    mask: u64,
+    tables: Vec<Rc<[u64]>>,
+    ids: HashMap<Rc<[u64]>, u32>,
+    exp_hcons: HConsign<Exp_<ExplicitBExp>, BuildHasher>,
+    actions: Actions,
+    // atoms outside the constraint are impossible
+    constraint: ExplicitBExp,
+    // set for good when a variable does not fit
+    exhausted: Option<Limit>,
+    stats: GkatStats,
+}
+
+impl ExplicitGkat {
+    // Most variables a manager takes, with tables of 2 MiB.
+    pub const MAX_VARS: usize = 24;
+
+    // A manager for at most vars variables, with tables of 2^vars bits.
+    pub fn new(vars: usize) -> Self {
+        let words = 1 << vars.saturating_sub(6);
+        let mask = if vars < 6 { (1 << (1 << vars)) - 1 } else { !0 };
+        let mut gkat = Self {
+            name_map: HashMap::default(),
+            var_names: vec![],
+            capacity: vars,
+            mask,
+            tables: vec![],
+            ids: HashMap::default(),
+            exp_hcons: HConsign::with_hasher(BuildHasher::default()),
+            actions: Actions::new(),
+            constraint: ONE,
+            exhausted: None,
+            stats: GkatStats::default(),
+        };
+        gkat.intern(vec![0; words]);
+        let mut one = vec![!0; words];
+        one[words - 1] = mask;
+        gkat.intern(one);
+        gkat
+    }
+
+    // Variables a manager needs for the given guards and extra variables,
+    // or None when there are more than max or MAX_VARS of them.
+    pub fn size_for(guards: &[Vec<String>], extra: Vec<String>, max: usize) -> Option<usize> {
+        let mut vars: Vec<&String> = guards.iter().flatten().chain(&extra).collect();
+        vars.sort();
+        vars.dedup();
+        (vars.len() <= max.min(Self::MAX_VARS)).then_some(vars.len())
+    }
+
+    // Make room for one more variable by doubling every table, which leaves
+    // the functions unchanged and their ids valid.
+    fn grow(&mut self) {
+        let bits = 1 << self.capacity;
+        self.capacity += 1;
+        if self.capacity < 6 {
+            self.mask = (1 << (1 << self.capacity)) - 1;
+        } else {
+            self.mask = !0;
+        }
+        for t in &mut self.tables {
+            *t = match bits {
+                ..64 => Rc::from([t[0] | t[0] << bits]),
+                _ => t.iter().chain(t.iter()).copied().collect(),
+            };
+        }
+        self.ids.clear();
+        for (id, t) in self.tables.iter().enumerate() {
+            self.ids.insert(t.clone(), id as u32);
+        }
+    }
+
+    fn table(&self, b: &ExplicitBExp) -> &[u64] {
+        &self.tables[b.0 as usize]
+    }
+
+    fn intern(&mut self, table: Vec<u64>) -> ExplicitBExp {
+        if let Some(id) = self.ids.get(table.as_slice()) {
+            return ExplicitBExp(*id);
+        }
+        let id = self.tables.len() as u32;
+        let table: Rc<[u64]> = table.into();
+        self.tables.push(table.clone());
+        self.ids.insert(table, id);
+        ExplicitBExp(id)
+    }
+
+    fn zip(
+        &mut self,
+        b1: &ExplicitBExp,
+        b2: &ExplicitBExp,
+        op: fn(u64, u64) -> u64,
+    ) -> ExplicitBExp {
+        let (t1, t2) = (self.table(b1), self.table(b2));
+        let table = t1.iter().zip(t2).map(|(x, y)| op(*x, *y)).collect();
+        self.intern(table)
+    }
+
+    // Whether op is zero on every word of b1, b2 and the constraint.
+    fn none(&self, b1: &ExplicitBExp, b2: &ExplicitBExp, op: fn(u64, u64) -> u64) -> bool {
+        let (t1, t2, k) = (self.table(b1), self.table(b2), self.table(&self.constraint));
+        (t1.iter().zip(t2).zip(k)).all(|((x, y), k)| op(*x, *y) & k == 0)
+    }
+}
+
+// Truth tables over the declared variables for isop, bit i of an atom
+// giving the value of variable i.
+struct Tables<'a> {
+    var_names: &'a [String],
+}
+
+impl Tables<'_> {
+    fn zip(f: &[bool], g: &[bool], op: fn(bool, bool) -> bool) -> Vec<bool> {
+        f.iter().zip(g).map(|(x, y)| op(*x, *y)).collect()
+    }
+
+    fn depends(f: &[bool], var: usize) -> bool {
+        (0..f.len()).any(|a| f[a] != f[a ^ (1 << var)])
+    }
+}
+
+impl Isop for Tables<'_> {
+    type F = Vec<bool>;
+
+    fn zero(&self) -> Vec<bool> {
+        vec![false; 1 << self.var_names.len()]
+    }
+
+    fn one(&self) -> Vec<bool> {
+        vec![true; 1 << self.var_names.len()]
+    }
+
+    // The highest variable, as the tables split on it first.
+    fn top(&self, lower: &Vec<bool>, upper: &Vec<bool>) -> usize {
+        let vars = 0..self.var_names.len();
+        let mut vars = vars.rev();
+        vars.find(|v| Self::depends(lower, *v) || Self::depends(upper, *v))
+            .unwrap()
+    }
+
+    fn split(&mut self, f: &Vec<bool>, var: usize) -> (Vec<bool>, Vec<bool>) {
+        let bit = 1 << var;
+        let high = (0..f.len()).map(|a| f[a | bit]).collect();
+        let low = (0..f.len()).map(|a| f[a & !bit]).collect();
+        (high, low)
+    }
+
+    fn join(&mut self, var: usize, high: &Vec<bool>, low: &Vec<bool>) -> Vec<bool> {
+        let bit = 1 << var;
+        (0..high.len())
+            .map(|a| if a & bit != 0 { high[a] } else { low[a] })
+            .collect()
+    }
+
+    fn and(&mut self, f: &Vec<bool>, g: &Vec<bool>) -> Vec<bool> {
+        Self::zip(f, g, |x, y| x && y)
+    }
+
+    fn and_not(&mut self, f: &Vec<bool>, g: &Vec<bool>) -> Vec<bool> {
+        Self::zip(f, g, |x, y| x && !y)
+    }
+
+    fn or(&mut self, f: &Vec<bool>, g: &Vec<bool>) -> Vec<bool> {
+        Self::zip(f, g, |x, y| x || y)
+    }
+
+    fn name(&self, var: usize) -> String {
+        self.var_names[var].clone()
+    }
+}
+
+impl Gkat<ExplicitBExp> for ExplicitGkat {
+    #[inline]
+    fn mk_zero(&mut self) -> ExplicitBExp {
+        ZERO
+    }
+
+    #[inline]
+    fn mk_one(&mut self) -> ExplicitBExp {
+        ONE
+    }
+
+    fn mk_var(&mut self, s: String) -> ExplicitBExp {
+        if let Some(x) = self.name_map.get(&s) {
+            return *x;
+        }
+        let i = self.var_names.len();
+        if i == Self::MAX_VARS {
+            self.exhausted = Some(Limit::Nodes);
+            return ZERO;
+        } else if i == self.capacity {
+            self.grow();
+        }
+        let words = self.table(&ONE).len();
+        let mut table: Vec<u64> = (0..words)
+            .map(|w| match i {
+                0..6 => PATTERNS[i],
+                _ if (w >> (i - 6)) & 1 == 1 => !0,
+                _ => 0,
+            })
+            .collect();
+        table[words - 1] &= self.mask;
+        let x = self.intern(table);
+        self.var_names.push(s.clone());
+        self.name_map.insert(s, x);
+        x
+    }
+
+    fn mk_and(&mut self, b1: &ExplicitBExp, b2: &ExplicitBExp) -> ExplicitBExp {
+        self.zip(b1, b2, |x, y| x & y)
+    }
+
+    fn mk_or(&mut self, b1: &ExplicitBExp, b2: &ExplicitBExp) -> ExplicitBExp {
+        self.zip(b1, b2, |x, y| x | y)
+    }
+
+    fn mk_not(&mut self, b: &ExplicitBExp) -> ExplicitBExp {
+        self.zip(b, &ONE, |x, one| !x & one)
+    }
+
+    fn add_constraint(&mut self, b: &ExplicitBExp) {
+        self.constraint = self.mk_and(&self.constraint.clone(), b);
+    }
+
+    fn is_false(&mut self, b: &ExplicitBExp) -> bool {
+        self.stats.is_false_calls += 1;
+        self.none(b, b, |x, _| x)
+    }
+
+    fn is_equiv(&mut self, b1: &ExplicitBExp, b2: &ExplicitBExp) -> bool {
+        self.stats.is_equiv_calls += 1;
+        b1 == b2 || self.none(b1, b2, |x, y| x ^ y)
+    }
+
+    fn pick_atom(&mut self, b: &ExplicitBExp) -> Option<Atom> {
+        let (t, k) = (self.table(b), self.table(&self.constraint));
+        let (w, x) = (t.iter().zip(k).map(|(x, k)| x & k).enumerate()).find(|(_, x)| *x != 0)?;
+        let a = w * 64 + x.trailing_zeros() as usize;
+        let mut atom: Vec<_> = (self.var_names.iter().enumerate())
+            .map(|(i, s)| (s.clone(), (a >> i) & 1 == 1))
+            .collect();
+        atom.sort();
+        Some(Atom(atom))
+    }
+
+    fn to_bexp(&mut self, b: &ExplicitBExp) -> parsing::BExp {
+        // only the declared variables, the tables do not depend on the rest
+        let n = self.var_names.len();
+        let table = self.table(b);
+        let bits: Vec<bool> = (0..1 << n)
+            .map(|a: usize| (table[a / 64] >> (a % 64)) & 1 == 1)
+            .collect();
+        let mut tables = Tables {
+            var_names: &self.var_names,
+        };
+        let (cubes, _) = isop(&mut tables, &bits, &bits);
+        factor_cover(&cubes)
+    }
+
+    // Word operations never run out of resources, only of variables.
+    fn set_limits(&mut self, _: &Limits) {}
+
+    #[inline]
+    fn exhausted(&self) -> Option<Limit> {
+        self.exhausted
+    }
+
+    fn set_reordering(&mut self, _: Reorder) {}
+
+    fn stats(&self) -> GkatStats {
+        self.stats.clone()
+    }
+
+    #[inline]
+    fn hashcons(&mut self, e: Exp_<ExplicitBExp>) -> Exp<ExplicitBExp> {
+        self.exp_hcons.mk(e)
+    }
+
+    fn actions(&mut self) -> &mut Actions {
+        &mut self.actions
+    }
+}
+
+#[test]
+fn test() {
+    use super::testing::{Lcg, parse_bexp, random_bexp};
+    // random guards over 8 variables, so tables span several words
+    let mut rng = Lcg::new(1);
+    let guards: Vec<_> = (0..40).map(|_| random_bexp(&mut rng, 5, 8)).collect();
+    // sized for fewer, so the tables grow past a word as variables appear
+    let mut gkat = ExplicitGkat::new(3);
+    let bs: Vec<_> = guards.iter().map(|b| gkat.from_bexp(b.clone())).collect();
+    for b in &bs {
+        let e = gkat.to_bexp(b);
+        assert_eq!(gkat.from_bexp(e), *b);
+    }
+    // the other backends agree with it on every query
+    fn agree<B: BExp, G: Gkat<B>>(
+        gkat: &mut ExplicitGkat,
+        bs: &[ExplicitBExp],
+        other: &mut G,
+        guards: &[parsing::BExp],
+    ) {
+        let cs: Vec<_> = guards.iter().map(|b| other.from_bexp(b.clone())).collect();
+        for i in 0..bs.len() {
+            assert_eq!(gkat.is_false(&bs[i]), other.is_false(&cs[i]));
+            for j in 0..bs.len() {
+                assert_eq!(
+                    gkat.is_equiv(&bs[i], &bs[j]),
+                    other.is_equiv(&cs[i], &cs[j])
+                );
+            }
+            if let Some(atom) = other.pick_atom(&cs[i]) {
+                let a = gkat.mk_atom(&atom);
+                let ab = gkat.mk_and(&a, &bs[i]);
+                assert!(!gkat.is_false(&ab));
+            }
+        }
+    }
+    agree(&mut gkat, &bs, &mut PureBDDGkat::new(), &guards);
+    #[cfg(feature = "sat")]
+    agree(&mut gkat, &bs, &mut SATGkat::new(), &guards);
+    #[cfg(feature = "sat")]
+    agree(&mut gkat, &bs, &mut AIGGkat::new(), &guards);
+    #[cfg(feature = "sat")]
+    agree(&mut gkat, &bs, &mut HybridGkat::with_budget(8), &guards);
+    // equal tables share one id
+    let x = gkat.mk_var("x0".to_string());
+    let b = gkat.from_bexp(parse_bexp("(or (and x0 x1) (and x0 (not x1)))"));
+    assert_eq!(b, x);
+    let k = gkat.mk_not(&x);
+    gkat.add_constraint(&k);
+    assert!(gkat.is_false(&x));
+}
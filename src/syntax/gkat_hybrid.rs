@@ -0,0 +1,308 @@
+use super::*;
+use crate::hash::BuildHasher;
+use crate::parsing;
+use hashconsing::{HConsign, HashConsign};
+use logicng::formulas::EncodedFormula;
+
+// Live BDD nodes before the guard being built is left to SAT.
+const NODE_BUDGET: usize = 1 << 18;
+
+// BExp of the portfolio: the formula of the guard, and its BDD while the
+// operations that built it stayed within the budget.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct HybridBExp {
+    bdd: Option<PureBDDBExp>,
+    sat: EncodedFormula,
+}
+
+impl BExp for HybridBExp {}
+
+// Gkat that answers queries with BDDs (PureBDDGkat) while they stay small,
+// and with SAT (SATGkat) for guards whose BDD would take the manager past
+// the budget. Guards built from a guard without a BDD have none either.
+pub struct HybridGkat {
+    exp_hcons: HConsign<Exp_<HybridBExp>, BuildHasher>,
+    actions: Actions,
+    bdd: PureBDDGkat,
+    sat: SATGkat,
+    // live nodes by default and under the current limits
+    budget: usize,
+    max_live: usize,
+    // the BDDs in use fill half the budget, so new guards go to SAT
+    full: bool,
+    // the BDD manager holds every constraint; otherwise its answers would
+    // be unsound and SAT takes every query
+    bdd_constraint: bool,
+    stats: GkatStats,
+}
+
+impl HybridGkat {
+    pub fn new() -> Self {
+        Self::with_budget(NODE_BUDGET)
+    }
+
+    pub fn with_budget(budget: usize) -> Self {
+        Self {
+            exp_hcons: HConsign::with_hasher(BuildHasher::default()),
+            actions: Actions::new(),
+            bdd: PureBDDGkat::new(),
+            sat: SATGkat::new(),
+            budget,
+            max_live: budget,
+            full: false,
+            bdd_constraint: true,
+            stats: GkatStats::default(),
+        }
+    }
+}
+
+impl Default for HybridGkat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HybridGkat {
+    // Run op on the BDD manager within the budget, counting a switch to
+    // SAT when it runs out. Garbage is collected when the budget is used
+    // up, which happens at most every half budget until the manager is full.
+    fn bounded<T>(&mut self, op: impl FnOnce(&mut PureBDDGkat) -> T) -> Option<T> {
+        if self.full {
+            return None;
+        }
+        if self.bdd.live() > self.max_live {
+            self.bdd.collect();
+            self.full = self.bdd.live() > self.max_live / 2;
+            if self.full {
+                self.stats.switches += 1;
+                return None;
+            }
+        }
+        let result = self.bdd.bounded(self.max_live, op);
+        if result.is_none() {
+            self.stats.switches += 1;
+        }
+        result
+    }
+
+    // BDD of a binary connective, when both operands have one.
+    fn lift(
+        &mut self,
+        b1: &HybridBExp,
+        b2: &HybridBExp,
+        op: impl FnOnce(&mut PureBDDGkat, &PureBDDBExp, &PureBDDBExp) -> PureBDDBExp,
+    ) -> Option<PureBDDBExp> {
+        let (x1, x2) = (b1.bdd.as_ref()?, b2.bdd.as_ref()?);
+        self.bounded(|man| op(man, x1, x2))
+    }
+
+    // Answer a query on the BDDs of guards, or None when SAT has to.
+    fn query<T>(
+        &mut self,
+        bs: &[&HybridBExp],
+        op: impl FnOnce(&mut PureBDDGkat, &[&PureBDDBExp]) -> T,
+    ) -> Option<T> {
+        if !self.bdd_constraint {
+            return None;
+        }
+        let xs: Option<Vec<_>> = bs.iter().map(|b| b.bdd.as_ref()).collect();
+        let xs = xs?;
+        self.bounded(|man| op(man, &xs))
+    }
+}
+
+impl Gkat<HybridBExp> for HybridGkat {
+    fn mk_zero(&mut self) -> HybridBExp {
+        HybridBExp {
+            bdd: Some(self.bdd.mk_zero()),
+            sat: self.sat.mk_zero(),
+        }
+    }
+
+    fn mk_one(&mut self) -> HybridBExp {
+        HybridBExp {
+            bdd: Some(self.bdd.mk_one()),
+            sat: self.sat.mk_one(),
+        }
+    }
+
+    fn mk_var(&mut self, s: String) -> HybridBExp {
+        HybridBExp {
+            bdd: Some(self.bdd.mk_var(s.clone())),
+            sat: self.sat.mk_var(s),
+        }
+    }
+
+    fn mk_and(&mut self, b1: &HybridBExp, b2: &HybridBExp) -> HybridBExp {
+        HybridBExp {
+            bdd: self.lift(b1, b2, |man, x1, x2| man.mk_and(x1, x2)),
+            sat: self.sat.mk_and(&b1.sat, &b2.sat),
+        }
+    }
+
+    fn mk_or(&mut self, b1: &HybridBExp, b2: &HybridBExp) -> HybridBExp {
+        HybridBExp {
+            bdd: self.lift(b1, b2, |man, x1, x2| man.mk_or(x1, x2)),
+            sat: self.sat.mk_or(&b1.sat, &b2.sat),
+        }
+    }
+
+    fn mk_not(&mut self, b: &HybridBExp) -> HybridBExp {
+        HybridBExp {
+            bdd: b.bdd.as_ref().map(|x| self.bdd.mk_not(x)),
+            sat: self.sat.mk_not(&b.sat),
+        }
+    }
+
+    fn add_constraint(&mut self, b: &HybridBExp) {
+        self.sat.add_constraint(&b.sat);
+        if self.bdd_constraint {
+            let added = match &b.bdd {
+                Some(x) => self.bounded(|man| man.add_constraint(x)),
+                None => None,
+            };
+            self.bdd_constraint = added.is_some();
+        }
+    }
+
+    fn is_false(&mut self, b: &HybridBExp) -> bool {
+        self.stats.is_false_calls += 1;
+        match self.query(&[b], |man, xs| man.is_false(xs[0])) {
+            Some(result) => result,
+            None => self.sat.is_false(&b.sat),
+        }
+    }
+
+    fn is_equiv(&mut self, b1: &HybridBExp, b2: &HybridBExp) -> bool {
+        self.stats.is_equiv_calls += 1;
+        match self.query(&[b1, b2], |man, xs| man.is_equiv(xs[0], xs[1])) {
+            Some(result) => result,
+            None => self.sat.is_equiv(&b1.sat, &b2.sat),
+        }
+    }
+
+    fn pick_atom(&mut self, b: &HybridBExp) -> Option<Atom> {
+        match self.query(&[b], |man, xs| man.pick_atom(xs[0])) {
+            Some(atom) => atom,
+            None => self.sat.pick_atom(&b.sat),
+        }
+    }
+
+    // BDDs print as irredundant covers, formulas as they were built.
+    fn to_bexp(&mut self, b: &HybridBExp) -> parsing::BExp {
+        match &b.bdd {
+            Some(x) => self.bdd.to_bexp(x),
+            None => self.sat.to_bexp(&b.sat),
+        }
+    }
+
+    // A node limit tightens the budget, so reaching it falls back to SAT
+    // instead of giving up.
+    fn set_limits(&mut self, limits: &Limits) {
+        let bdd_limits = Limits {
+            max_nodes: None,
+            ..*limits
+        };
+        self.bdd.set_limits(&bdd_limits);
+        self.sat.set_limits(limits);
+        self.max_live = limits.max_nodes.map_or(self.budget, |n| n.min(self.budget));
+        self.full = false;
+    }
+
+    #[inline]
+    fn exhausted(&self) -> Option<Limit> {
+        self.bdd.exhausted().or(self.sat.exhausted())
+    }
+
+    fn set_reordering(&mut self, method: Reorder) {
+        self.bdd.set_reordering(method);
+    }
+
+    fn stats(&self) -> GkatStats {
+        let (bdd, sat) = (self.bdd.stats(), self.sat.stats());
+        GkatStats {
+            sat_calls: sat.sat_calls,
+            cache_hits: sat.cache_hits,
+            nodes: bdd.nodes,
+            peak_nodes: bdd.peak_nodes,
+            ..self.stats.clone()
+        }
+    }
+
+    #[inline]
+    fn hashcons(&mut self, e: Exp_<HybridBExp>) -> Exp<HybridBExp> {
+        self.exp_hcons.mk(e)
+    }
+
+    fn actions(&mut self) -> &mut Actions {
+        &mut self.actions
+    }
+}
+
+#[test]
+fn test() {
+    use super::testing::{parse_bexp, run_kernels};
+    // small guards stay BDDs
+    let mut gkat = HybridGkat::with_budget(8);
+    let b1 = gkat.from_bexp(parse_bexp("(or (and a b) (and a (not b)))"));
+    let b2 = gkat.mk_var("a".to_string());
+    assert!(b1.bdd.is_some() && gkat.is_equiv(&b1, &b2));
+    assert_eq!(gkat.to_bexp(&b1).to_string(), "a");
+    assert_eq!(gkat.stats().switches, 0);
+    // pairs (xi and yi) with every x ordered before every y blow up the
+    // BDD past the budget, so SAT takes over
+    for v in ["x", "y"] {
+        for i in 0..8 {
+            gkat.mk_var(format!("{}{}", v, i));
+        }
+    }
+    let pairs = |pair: &str| {
+        let pairs: Vec<_> = (0..8).map(|i| pair.replace("{}", &i.to_string())).collect();
+        let last = pairs[0].clone();
+        pairs[1..]
+            .iter()
+            .fold(last, |acc, p| format!("(or {} {})", p, acc))
+    };
+    let p1 = gkat.from_bexp(parse_bexp(&pairs("(and x{} y{})")));
+    let p2 = gkat.from_bexp(parse_bexp(&pairs("(not (or (not x{}) (not y{})))")));
+    assert!(p1.bdd.is_none() && p2.bdd.is_none());
+    let switches = gkat.stats().switches;
+    assert!(switches > 0);
+    assert!(gkat.is_equiv(&p1, &p2));
+    let np2 = gkat.mk_not(&p2);
+    assert!(!gkat.is_equiv(&p1, &np2));
+    let p = gkat.mk_and(&p1, &np2);
+    assert!(gkat.is_false(&p));
+    assert!(gkat.stats().sat_calls > 0);
+    // guards built from SAT guards stay there without new switches
+    assert_eq!(gkat.stats().switches, switches);
+    let atom = gkat.pick_atom(&p1).unwrap();
+    let a = gkat.mk_atom(&atom);
+    let ap = gkat.mk_and(&a, &p1);
+    assert!(!gkat.is_false(&ap));
+    // a constraint that does not fit leaves every query to SAT
+    gkat.add_constraint(&np2);
+    assert!(gkat.is_false(&p1));
+    let x0 = gkat.mk_var("x0".to_string());
+    let x0p = gkat.mk_or(&x0, &p1);
+    assert!(gkat.is_equiv(&x0p, &x0));
+    // a node limit moves guards to SAT instead of giving up
+    let mut gkat = HybridGkat::new();
+    gkat.set_limits(&Limits {
+        max_nodes: Some(64),
+        ..Limits::default()
+    });
+    for v in ["x", "y"] {
+        for i in 0..8 {
+            gkat.mk_var(format!("{}{}", v, i));
+        }
+    }
+    let p1 = gkat.from_bexp(parse_bexp(&pairs("(and x{} y{})")));
+    let p2 = gkat.from_bexp(parse_bexp(&pairs("(not (or (not x{}) (not y{})))")));
+    assert!(p1.bdd.is_none() && gkat.is_equiv(&p1, &p2));
+    assert_eq!(gkat.exhausted(), None);
+    // small programs never leave the BDDs
+    let mut gkat = HybridGkat::new();
+    run_kernels(&mut gkat);
+    assert_eq!(gkat.stats().switches, 0);
+}
@@ -0,0 +1,563 @@
+use super::edge::*;
+use super::*;
+use crate::hash::{BuildHasher, HashMap};
+use crate::parsing;
+use core::fmt;
+use hashconsing::{HConsign, HashConsign};
+use std::{cell::RefCell, fmt::Debug, hash::Hash, rc::Rc, time::Instant};
+
+// Variable of the terminal, below every real variable in the order.
+const TERMINAL: u32 = u32::MAX;
+// Variable of a slot on the free list.
+const FREE: u32 = u32::MAX - 1;
+
+// Entries of the computed table, a power of two between these that
+// grows with the live nodes.
+const CACHE_MIN: usize = 1 << 16;
+const CACHE_MAX: usize = 1 << 22;
+
+// Live nodes before the first garbage collection.
+const GC_START: usize = 1 << 16;
+
+// Decision node on var; high is never complemented, which makes the
+// representation canonical.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Node {
+    var: u32,
+    high: Edge,
+    low: Edge,
+}
+
+#[inline]
+fn cache_slot(f: Edge, g: Edge, h: Edge, slots: usize) -> usize {
+    let x = (f as u64).wrapping_mul(0x9e3779b97f4a7c15)
+        ^ (g as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)
+        ^ (h as u64).wrapping_mul(0x165667b19e3779f9);
+    (x >> 32) as usize & (slots - 1)
+}
+
+// External reference counts by node index, shared by the manager and
+// every PureBDDBExp. Nodes reachable from a counted node survive collection.
+type Refs = Rc<RefCell<Vec<u32>>>;
+
+// BExp based on the pure-Rust BDD.
+pub struct PureBDDBExp {
+    refs: Refs,
+    edge: Edge,
+}
+
+impl Drop for PureBDDBExp {
+    fn drop(&mut self) {
+        self.refs.borrow_mut()[index(self.edge)] -= 1;
+    }
+}
+
+impl BExp for PureBDDBExp {}
+
+impl Clone for PureBDDBExp {
+    fn clone(&self) -> Self {
+        self.refs.borrow_mut()[index(self.edge)] += 1;
+        Self {
+            refs: self.refs.clone(),
+            edge: self.edge,
+        }
+    }
+}
+
+impl Debug for PureBDDBExp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        // variable names live in the PureBDDGkat, see to_bexp
+        write!(f, "PureBDDBExp({})", self.edge)
+    }
+}
+
+impl Hash for PureBDDBExp {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.edge.hash(state);
+    }
+}
+
+impl PartialEq for PureBDDBExp {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.edge == other.edge
+    }
+}
+
+impl Eq for PureBDDBExp {}
+
+// Gkat based on a pure-Rust BDD with complement edges. Variables are
+// ordered by their index, which is the order they were created in.
+pub struct PureBDDGkat {
+    name_map: HashMap<String, PureBDDBExp>,
+    // names by variable index
+    var_names: Vec<String>,
+    exp_hcons: HConsign<Exp_<PureBDDBExp>, BuildHasher>,
+    actions: Actions,
+    // atoms outside the constraint are impossible
+    constraint: Option<PureBDDBExp>,
+    // node arena, unique table and free slots of the arena
+    nodes: Vec<Node>,
+    unique: HashMap<Node, u32>,
+    free: Vec<u32>,
+    refs: Refs,
+    // computed table of ite, direct mapped and lossy: (f, g, h, ite(f, g, h))
+    cache: Vec<[Edge; 4]>,
+    // cache misses so far, to poll the deadline now and then
+    misses: usize,
+    // live nodes that trigger the next garbage collection
+    gc_threshold: usize,
+    // resource limits
+    deadline: Option<Instant>,
+    max_nodes: Option<usize>,
+    exhausted: Option<Limit>,
+    // live nodes the current bounded operation may grow the manager to
+    budget: Option<usize>,
+    over_budget: bool,
+    stats: GkatStats,
+}
+
+impl PureBDDGkat {
+    pub fn new() -> Self {
+        let terminal = Node {
+            var: TERMINAL,
+            high: ONE,
+            low: ONE,
+        };
+        Self {
+            name_map: HashMap::default(),
+            var_names: vec![],
+            exp_hcons: HConsign::with_hasher(BuildHasher::default()),
+            actions: Actions::new(),
+            constraint: None,
+            nodes: vec![terminal],
+            unique: HashMap::default(),
+            free: vec![],
+            refs: Rc::new(RefCell::new(vec![0])),
+            cache: vec![[ZERO; 4]; CACHE_MIN],
+            misses: 0,
+            gc_threshold: GC_START,
+            deadline: None,
+            max_nodes: None,
+            exhausted: None,
+            budget: None,
+            over_budget: false,
+            stats: GkatStats::default(),
+        }
+    }
+}
+
+impl PureBDDGkat {
+    fn wrap(&mut self, edge: Edge) -> PureBDDBExp {
+        self.refs.borrow_mut()[index(edge)] += 1;
+        PureBDDBExp {
+            refs: self.refs.clone(),
+            edge,
+        }
+    }
+
+    pub fn live(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    #[inline]
+    fn var(&self, e: Edge) -> u32 {
+        self.nodes[index(e)].var
+    }
+
+    // Cofactors of e with respect to var, high first.
+    #[inline]
+    fn cofactors(&self, e: Edge, var: u32) -> (Edge, Edge) {
+        let n = self.nodes[index(e)];
+        if n.var != var {
+            return (e, e);
+        }
+        let c = e & 1;
+        (n.high ^ c, n.low ^ c)
+    }
+
+    // The node (var ? high : low), reduced and shared through the unique
+    // table.
+    fn mk(&mut self, var: u32, high: Edge, low: Edge) -> Edge {
+        if high == low {
+            return high;
+        }
+        if is_complement(high) {
+            return self.mk(var, high ^ 1, low ^ 1) ^ 1;
+        }
+        let node = Node { var, high, low };
+        if let Some(i) = self.unique.get(&node) {
+            return i << 1;
+        }
+        let i = match self.free.pop() {
+            Some(i) => {
+                self.nodes[i as usize] = node;
+                i
+            }
+            None => {
+                self.nodes.push(node);
+                self.refs.borrow_mut().push(0);
+                (self.nodes.len() - 1) as u32
+            }
+        };
+        self.unique.insert(node, i);
+        let live = self.live();
+        if self.budget.is_some_and(|n| live > n) {
+            self.over_budget = true;
+        }
+        self.stats.peak_nodes = self.stats.peak_nodes.max(live);
+        if self.max_nodes.is_some_and(|n| live > n) {
+            self.exhausted.get_or_insert(Limit::Nodes);
+        }
+        i << 1
+    }
+
+    // If-then-else, the operation every connective reduces to.
+    fn ite(&mut self, f: Edge, g: Edge, h: Edge) -> Edge {
+        if self.exhausted.is_some() || self.over_budget {
+            return ZERO;
+        }
+        // substitute constants for f in g and h
+        let g = if g == f {
+            ONE
+        } else if g == f ^ 1 {
+            ZERO
+        } else {
+            g
+        };
+        let h = if h == f {
+            ZERO
+        } else if h == f ^ 1 {
+            ONE
+        } else {
+            h
+        };
+        // terminal cases
+        if f == ONE || g == h {
+            return g;
+        } else if f == ZERO {
+            return h;
+        } else if g == ONE && h == ZERO {
+            return f;
+        } else if g == ZERO && h == ONE {
+            return f ^ 1;
+        }
+        // standard triples: f and g regular
+        let (f, g, h) = if is_complement(f) {
+            (f ^ 1, h, g)
+        } else {
+            (f, g, h)
+        };
+        if is_complement(g) {
+            return self.ite(f, g ^ 1, h ^ 1) ^ 1;
+        }
+        let slot = cache_slot(f, g, h, self.cache.len());
+        let [f2, g2, h2, r] = self.cache[slot];
+        if (f2, g2, h2) == (f, g, h) {
+            return r;
+        }
+        self.misses += 1;
+        if self.misses.is_multiple_of(4096) && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.exhausted.get_or_insert(Limit::Timeout);
+        }
+        let var = self.var(f).min(self.var(g)).min(self.var(h));
+        let (f1, f0) = self.cofactors(f, var);
+        let (g1, g0) = self.cofactors(g, var);
+        let (h1, h0) = self.cofactors(h, var);
+        let high = self.ite(f1, g1, h1);
+        let low = self.ite(f0, g0, h0);
+        // results cut short are wrong and stay out of the computed table
+        if self.exhausted.is_some() || self.over_budget {
+            return ZERO;
+        }
+        let r = self.mk(var, high, low);
+        self.cache[slot] = [f, g, h, r];
+        r
+    }
+
+    fn and(&mut self, e1: Edge, e2: Edge) -> Edge {
+        self.ite(e1, e2, ZERO)
+    }
+
+    // Collect garbage once enough nodes are live.
+    fn collect_garbage(&mut self) {
+        if self.live() >= self.gc_threshold {
+            self.collect();
+        }
+    }
+
+    // Free the nodes unreachable from a referenced node. Only called
+    // between operations, whose intermediate results hold no references.
+    pub fn collect(&mut self) {
+        let mut marked = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = {
+            let refs = self.refs.borrow();
+            (0..self.nodes.len()).filter(|i| refs[*i] > 0).collect()
+        };
+        marked[0] = true;
+        while let Some(i) = stack.pop() {
+            if marked[i] {
+                continue;
+            }
+            marked[i] = true;
+            let n = self.nodes[i];
+            stack.push(index(n.high));
+            stack.push(index(n.low));
+        }
+        for (i, node) in self.nodes.iter_mut().enumerate() {
+            if !marked[i] && node.var != FREE {
+                self.unique.remove(node);
+                node.var = FREE;
+                self.free.push(i as u32);
+            }
+        }
+        let slots = self.live().next_power_of_two().clamp(CACHE_MIN, CACHE_MAX);
+        self.cache = vec![[ZERO; 4]; slots];
+        self.gc_threshold = self.gc_threshold.max(2 * self.live());
+    }
+
+    // Run op unless it takes the manager past budget live nodes, in which
+    // case its result is dropped.
+    pub fn bounded<T>(&mut self, budget: usize, op: impl FnOnce(&mut Self) -> T) -> Option<T> {
+        self.budget = Some(budget);
+        let result = op(self);
+        self.budget = None;
+        if std::mem::take(&mut self.over_budget) {
+            return None;
+        }
+        Some(result)
+    }
+
+    // Apply op to the edges of the arguments after collecting garbage.
+    fn apply(&mut self, op: impl FnOnce(&mut Self) -> Edge) -> PureBDDBExp {
+        self.collect_garbage();
+        let e = op(self);
+        self.stats.nodes = self.live();
+        self.wrap(e)
+    }
+}
+
+impl Isop for PureBDDGkat {
+    type F = Edge;
+
+    fn zero(&self) -> Edge {
+        ZERO
+    }
+
+    fn one(&self) -> Edge {
+        ONE
+    }
+
+    fn top(&self, lower: &Edge, upper: &Edge) -> usize {
+        self.var(*lower).min(self.var(*upper)) as usize
+    }
+
+    fn split(&mut self, f: &Edge, var: usize) -> (Edge, Edge) {
+        self.cofactors(*f, var as u32)
+    }
+
+    fn join(&mut self, var: usize, high: &Edge, low: &Edge) -> Edge {
+        self.mk(var as u32, *high, *low)
+    }
+
+    fn and(&mut self, f: &Edge, g: &Edge) -> Edge {
+        PureBDDGkat::and(self, *f, *g)
+    }
+
+    fn and_not(&mut self, f: &Edge, g: &Edge) -> Edge {
+        PureBDDGkat::and(self, *f, *g ^ 1)
+    }
+
+    fn or(&mut self, f: &Edge, g: &Edge) -> Edge {
+        self.ite(*f, ONE, *g)
+    }
+
+    fn name(&self, var: usize) -> String {
+        self.var_names[var].clone()
+    }
+}
+
+impl Default for PureBDDGkat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gkat<PureBDDBExp> for PureBDDGkat {
+    fn mk_zero(&mut self) -> PureBDDBExp {
+        self.wrap(ZERO)
+    }
+
+    fn mk_one(&mut self) -> PureBDDBExp {
+        self.wrap(ONE)
+    }
+
+    fn mk_var(&mut self, s: String) -> PureBDDBExp {
+        if let Some(x) = self.name_map.get(&s) {
+            return x.clone();
+        }
+        let var = self.var_names.len() as u32;
+        self.var_names.push(s.clone());
+        let x = self.apply(|man| man.mk(var, ONE, ZERO));
+        self.name_map.insert(s, x.clone());
+        x
+    }
+
+    fn mk_and(&mut self, b1: &PureBDDBExp, b2: &PureBDDBExp) -> PureBDDBExp {
+        self.apply(|man| man.and(b1.edge, b2.edge))
+    }
+
+    fn mk_or(&mut self, b1: &PureBDDBExp, b2: &PureBDDBExp) -> PureBDDBExp {
+        self.apply(|man| man.ite(b1.edge, ONE, b2.edge))
+    }
+
+    fn mk_not(&mut self, b: &PureBDDBExp) -> PureBDDBExp {
+        self.wrap(b.edge ^ 1)
+    }
+
+    fn add_constraint(&mut self, b: &PureBDDBExp) {
+        let k = match self.constraint.clone() {
+            Some(k) => self.mk_and(&k, b),
+            None => b.clone(),
+        };
+        self.constraint = Some(k);
+    }
+
+    fn is_false(&mut self, b: &PureBDDBExp) -> bool {
+        self.stats.is_false_calls += 1;
+        if self.exhausted.is_some() {
+            return true;
+        }
+        match self.constraint.as_ref().map(|k| k.edge) {
+            Some(k) => self.apply(|man| man.and(b.edge, k)).edge == ZERO,
+            None => b.edge == ZERO,
+        }
+    }
+
+    fn is_equiv(&mut self, b1: &PureBDDBExp, b2: &PureBDDBExp) -> bool {
+        self.stats.is_equiv_calls += 1;
+        if self.exhausted.is_some() {
+            return true;
+        }
+        match self.constraint.as_ref().map(|k| k.edge) {
+            // b1 and b2 agree wherever the constraint holds
+            Some(k) => {
+                let differ = self.apply(|man| {
+                    let xor = man.ite(b1.edge, b2.edge ^ 1, b2.edge);
+                    man.and(xor, k)
+                });
+                differ.edge == ZERO
+            }
+            None => b1 == b2,
+        }
+    }
+
+    fn pick_atom(&mut self, b: &PureBDDBExp) -> Option<Atom> {
+        if self.exhausted.is_some() {
+            return None;
+        }
+        let b = match self.constraint.clone() {
+            Some(k) => self.mk_and(b, &k),
+            None => b.clone(),
+        };
+        if b.edge == ZERO {
+            return None;
+        }
+        // follow a path to the one terminal; variables off the path are
+        // resolved to false
+        let mut values = vec![false; self.var_names.len()];
+        let mut e = b.edge;
+        while e != ONE {
+            let var = self.var(e);
+            let (high, low) = self.cofactors(e, var);
+            if high != ZERO {
+                values[var as usize] = true;
+                e = high;
+            } else {
+                e = low;
+            }
+        }
+        let mut atom: Vec<_> = self.var_names.iter().cloned().zip(values).collect();
+        atom.sort();
+        Some(Atom(atom))
+    }
+
+    fn to_bexp(&mut self, b: &PureBDDBExp) -> parsing::BExp {
+        self.collect_garbage();
+        let (cubes, _) = isop(self, &b.edge, &b.edge);
+        factor_cover(&cubes)
+    }
+
+    fn set_limits(&mut self, limits: &Limits) {
+        self.deadline = limits.deadline();
+        self.max_nodes = limits.max_nodes;
+        self.exhausted = None;
+    }
+
+    #[inline]
+    fn exhausted(&self) -> Option<Limit> {
+        self.exhausted
+    }
+
+    // The order is fixed once variables are created.
+    fn set_reordering(&mut self, _: Reorder) {}
+
+    fn stats(&self) -> GkatStats {
+        self.stats.clone()
+    }
+
+    #[inline]
+    fn hashcons(&mut self, e: Exp_<PureBDDBExp>) -> Exp<PureBDDBExp> {
+        self.exp_hcons.mk(e)
+    }
+
+    fn actions(&mut self) -> &mut Actions {
+        &mut self.actions
+    }
+}
+
+#[test]
+fn test() {
+    use super::testing::parse_bexp;
+    let mut gkat = PureBDDGkat::new();
+    // equal functions share one edge
+    let b1 = gkat.from_bexp(parse_bexp("(or (and a b) (and a (not b)))"));
+    let b2 = gkat.mk_var("a".to_string());
+    assert_eq!(b1, b2);
+    let nb2 = gkat.mk_not(&b2);
+    assert_eq!(gkat.mk_not(&nb2), b2);
+    let b = gkat.from_bexp(parse_bexp("(or (and a b) (and (not a) c))"));
+    assert_eq!(
+        gkat.to_bexp(&b).to_string(),
+        "(or (and (not a) c) (and a b))"
+    );
+    let atom = gkat.pick_atom(&b).unwrap();
+    let a = gkat.mk_atom(&atom);
+    let ab = gkat.mk_and(&a, &b);
+    assert!(!gkat.is_false(&ab));
+    // collected nodes are reused and results stay canonical
+    gkat.gc_threshold = 0;
+    for i in 0..50 {
+        let x = gkat.mk_var(format!("x{}", i));
+        let y = gkat.mk_and(&x, &b);
+        drop(y);
+    }
+    let before = gkat.live();
+    gkat.collect();
+    assert!(gkat.live() < before);
+    let b3 = gkat.from_bexp(parse_bexp("(or (and (not a) c) (and a b))"));
+    assert_eq!(b3, b);
+    // an operation cut short leaves no wrong results behind
+    let d = gkat.from_bexp(parse_bexp("(or d e)"));
+    let live = gkat.live();
+    assert!(gkat.bounded(live, |man| man.mk_and(&b, &d)).is_none());
+    let bd = gkat.mk_and(&b, &d);
+    let bd2 = gkat.from_bexp(parse_bexp("(and (or (and (not a) c) (and a b)) (or e d))"));
+    assert!(!gkat.is_false(&bd) && bd == bd2);
+    let k = gkat.mk_not(&b2);
+    gkat.add_constraint(&k);
+    assert!(gkat.is_false(&b2));
+    let c = gkat.mk_var("c".to_string());
+    assert!(gkat.is_equiv(&b, &c));
+}
@@ -1,32 +1,118 @@
+use super::tseitin::Tseitin;
 use super::*;
-use gxhash::{GxBuildHasher, HashMap};
+use crate::hash::{BuildHasher, HashMap};
+use crate::parsing;
 use hashconsing::{HConsign, HashConsign};
 use logicng::{
-    formulas::{EncodedFormula, FormulaFactory},
-    solver::minisat::*,
+    formulas::{EncodedFormula, Formula, FormulaFactory},
+    solver::minisat::sat::{MsLit, Tristate, not},
 };
+use std::time::Instant;
 
 impl BExp for EncodedFormula {}
 
 // Gkat based on BDD.
 pub struct SATGkat {
     name_map: HashMap<String, EncodedFormula>,
-    exp_hcons: HConsign<Exp_<EncodedFormula>, GxBuildHasher>,
-    // formula manager and solver
-    solver: MiniSat,
+    exp_hcons: HConsign<Exp_<EncodedFormula>, BuildHasher>,
+    actions: Actions,
+    // formula manager and incremental solver; queries assume the literal
+    // of the root
+    cnf: Tseitin<EncodedFormula>,
     man: FormulaFactory,
+    // atoms outside the constraint are impossible
+    constraint: EncodedFormula,
+    // resource limits
+    deadline: Option<Instant>,
+    max_conflicts: Option<usize>,
+    exhausted: Option<Limit>,
+    stats: GkatStats,
     // caching
     is_false_cache: HashMap<EncodedFormula, bool>,
+    is_equiv_cache: HashMap<(EncodedFormula, EncodedFormula), bool>,
+}
+
+impl Default for SATGkat {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SATGkat {
     pub fn new() -> Self {
         Self {
             name_map: HashMap::default(),
-            exp_hcons: HConsign::with_hasher(GxBuildHasher::default()),
-            solver: MiniSat::new(),
+            exp_hcons: HConsign::with_hasher(BuildHasher::default()),
+            actions: Actions::new(),
+            cnf: Tseitin::new(),
             man: FormulaFactory::new(),
+            constraint: EncodedFormula::constant(true),
+            deadline: None,
+            max_conflicts: None,
+            exhausted: None,
+            stats: GkatStats::default(),
             is_false_cache: HashMap::default(),
+            is_equiv_cache: HashMap::default(),
+        }
+    }
+}
+
+// Literal equivalent to b, adding the clauses of the subformulas not
+// encoded yet.
+fn encode(cnf: &mut Tseitin<EncodedFormula>, man: &FormulaFactory, b: EncodedFormula) -> MsLit {
+    if let Some(x) = cnf.get(&b) {
+        return x;
+    }
+    let x = match b.unpack(man) {
+        Formula::True => cnf.one(),
+        Formula::False => not(encode(cnf, man, EncodedFormula::constant(true))),
+        Formula::Lit(l) if l.phase() => cnf.input(),
+        Formula::Lit(l) => not(encode(cnf, man, l.variable().into())),
+        Formula::Not(b) => not(encode(cnf, man, b)),
+        Formula::And(ops) => {
+            let ops: Vec<_> = ops.collect();
+            let ops: Vec<_> = ops.into_iter().map(|op| encode(cnf, man, op)).collect();
+            cnf.and(&ops)
+        }
+        Formula::Or(ops) => {
+            let ops: Vec<_> = ops.collect();
+            let ops: Vec<_> = ops.into_iter().map(|op| not(encode(cnf, man, op))).collect();
+            not(cnf.and(&ops))
+        }
+        Formula::Impl((b1, b2)) => {
+            let ops = [encode(cnf, man, b1), not(encode(cnf, man, b2))];
+            not(cnf.and(&ops))
+        }
+        Formula::Equiv((b1, b2)) => {
+            let (x1, x2) = (encode(cnf, man, b1), encode(cnf, man, b2));
+            cnf.equiv(x1, x2)
+        }
+        Formula::Pbc(_) | Formula::Cc(_) => unreachable!(),
+    };
+    cnf.insert(b, x);
+    x
+}
+
+impl SATGkat {
+    // Literal to assume for solving b.
+    fn assume(&mut self, b: EncodedFormula) -> MsLit {
+        self.cnf.restart();
+        encode(&mut self.cnf, &self.man, b)
+    }
+
+    // Decide whether b is satisfiable within the conflict budget.
+    fn solve(&mut self, b: EncodedFormula) -> Tristate {
+        self.stats.sat_calls += 1;
+        match self.max_conflicts {
+            None => {
+                let x = self.assume(b);
+                self.cnf.solve(vec![x])
+            }
+            Some(n) => {
+                let mut cnf = Tseitin::new();
+                let x = encode(&mut cnf, &self.man, b);
+                cnf.search(vec![x], n)
+            }
         }
     }
 }
@@ -44,11 +130,11 @@ impl Gkat<EncodedFormula> for SATGkat {
 
     fn mk_var(&mut self, s: String) -> EncodedFormula {
         if let Some(x) = self.name_map.get(&s) {
-            return x.clone();
+            return *x;
         }
         let x = self.man.variable(s.as_str());
-        self.name_map.insert(s, x.clone());
-        return x;
+        self.name_map.insert(s, x);
+        x
     }
 
     fn mk_and(&mut self, b1: &EncodedFormula, b2: &EncodedFormula) -> EncodedFormula {
@@ -56,9 +142,7 @@ impl Gkat<EncodedFormula> for SATGkat {
             return self.mk_zero();
         } else if *b1 == self.mk_one() {
             return *b2;
-        } else if *b2 == self.mk_one() {
-            return *b1;
-        } else if b1 == b2 {
+        } else if *b2 == self.mk_one() || b1 == b2 {
             return *b1;
         }
         self.man.and(&[*b1, *b2])
@@ -82,33 +166,218 @@ impl Gkat<EncodedFormula> for SATGkat {
         self.man.not(*b)
     }
 
+    fn add_constraint(&mut self, b: &EncodedFormula) {
+        self.constraint = self.mk_and(&self.constraint.clone(), b);
+        self.is_false_cache.clear();
+        self.is_equiv_cache.clear();
+    }
+
     fn is_false(&mut self, b: &EncodedFormula) -> bool {
-        if b == &self.mk_zero() {
+        self.stats.is_false_calls += 1;
+        let b = &self.mk_and(b, &self.constraint.clone());
+        if self.exhausted.is_some() || b == &self.mk_zero() {
             return true;
         } else if b == &self.mk_one() {
             return false;
         } else if let Some(result) = self.is_false_cache.get(b) {
+            self.stats.cache_hits += 1;
             return *result;
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.exhausted = Some(Limit::Timeout);
+            return true;
         }
-        self.solver.add(*b, &self.man);
-        let result = match self.solver.sat() {
-            sat::Tristate::True => false,
-            sat::Tristate::False => true,
-            sat::Tristate::Undef => panic!("unknown"),
+        let result = match self.solve(*b) {
+            Tristate::True => false,
+            Tristate::False => true,
+            Tristate::Undef => {
+                self.exhausted = Some(Limit::Conflicts);
+                return true;
+            }
         };
-        self.solver.reset();
         self.is_false_cache.insert(*b, result);
-        return result;
+        result
     }
 
     fn is_equiv(&mut self, b1: &EncodedFormula, b2: &EncodedFormula) -> bool {
+        self.stats.is_equiv_calls += 1;
+        if b1 == b2 {
+            return true;
+        }
+        let key = (*b1, *b2);
+        let cached = self.is_equiv_cache.get(&key);
+        if let Some(result) = cached.or_else(|| self.is_equiv_cache.get(&(*b2, *b1))) {
+            self.stats.cache_hits += 1;
+            return *result;
+        }
         let b = self.man.equivalence(*b1, *b2);
         let nb = self.mk_not(&b);
-        self.is_false(&nb)
+        let result = self.is_false(&nb);
+        // an exhausted budget makes the answer meaningless
+        if self.exhausted.is_none() {
+            self.is_equiv_cache.insert(key, result);
+        }
+        result
+    }
+
+    fn pick_atom(&mut self, b: &EncodedFormula) -> Option<Atom> {
+        if self.exhausted.is_some() {
+            return None;
+        }
+        let b = self.mk_and(b, &self.constraint.clone());
+        self.stats.sat_calls += 1;
+        let x = self.assume(b);
+        match self.cnf.solve(vec![x]) {
+            Tristate::True => {
+                // variables outside b are not encoded and may be either
+                let mut atom: Vec<_> = self
+                    .name_map
+                    .iter()
+                    .map(|(s, x)| (s.clone(), self.cnf.value(x)))
+                    .collect();
+                atom.sort();
+                Some(Atom(atom))
+            }
+            Tristate::False => None,
+            // unbounded solving always decides
+            Tristate::Undef => unreachable!(),
+        }
+    }
+
+    fn to_bexp(&mut self, b: &EncodedFormula) -> parsing::BExp {
+        use parsing::BExp::*;
+        match b.unpack(&self.man) {
+            Formula::True => One,
+            Formula::False => Zero,
+            Formula::Lit(l) => {
+                let x = PBool(l.name(&self.man).to_string());
+                if l.phase() { x } else { Not(Box::new(x)) }
+            }
+            Formula::Not(b) => Not(Box::new(self.to_bexp(&b))),
+            Formula::And(ops) => {
+                let ops: Vec<_> = ops.collect();
+                let mut ops = ops.iter().rev().map(|op| self.to_bexp(op));
+                let last = ops.next().unwrap_or(One);
+                ops.fold(last, |acc, b| And(Box::new(b), Box::new(acc)))
+            }
+            Formula::Or(ops) => {
+                let ops: Vec<_> = ops.collect();
+                let mut ops = ops.iter().rev().map(|op| self.to_bexp(op));
+                let last = ops.next().unwrap_or(Zero);
+                ops.fold(last, |acc, b| Or(Box::new(b), Box::new(acc)))
+            }
+            Formula::Impl((b1, b2)) => {
+                let nb1 = Not(Box::new(self.to_bexp(&b1)));
+                Or(Box::new(nb1), Box::new(self.to_bexp(&b2)))
+            }
+            Formula::Equiv((b1, b2)) => {
+                let x1 = self.to_bexp(&b1);
+                let x2 = self.to_bexp(&b2);
+                let both = And(Box::new(x1.clone()), Box::new(x2.clone()));
+                let nx1 = Not(Box::new(x1));
+                let nx2 = Not(Box::new(x2));
+                let neither = And(Box::new(nx1), Box::new(nx2));
+                Or(Box::new(both), Box::new(neither))
+            }
+            Formula::Pbc(_) | Formula::Cc(_) => unreachable!(),
+        }
+    }
+
+    fn set_limits(&mut self, limits: &Limits) {
+        self.deadline = limits.deadline();
+        self.max_conflicts = limits.max_conflicts;
+        self.exhausted = None;
+    }
+
+    #[inline]
+    fn exhausted(&self) -> Option<Limit> {
+        self.exhausted
+    }
+
+    fn set_reordering(&mut self, _: Reorder) {}
+
+    fn stats(&self) -> GkatStats {
+        self.stats.clone()
     }
 
     #[inline]
     fn hashcons(&mut self, e: Exp_<EncodedFormula>) -> Exp<EncodedFormula> {
         self.exp_hcons.mk(e)
     }
+
+    fn actions(&mut self) -> &mut Actions {
+        &mut self.actions
+    }
+}
+
+#[test]
+fn test() {
+    use crate::{
+        kernel1, kernel2,
+        parsing::{Goal, parse},
+    };
+    use super::testing::{Lcg, random_bexp};
+    let s = "(constraint (not (and b1 b2))) (constraint (or (not b3) b4))
+             (if b1 (if b2 p1 p2) (while (and b3 (not b4)) p3)) (if b1 p2 (test 1)) (equiv 1)";
+    let input = parse(s.to_string()).unwrap();
+    let mut gkat = SATGkat::new();
+    let (b1, b2) = (gkat.mk_var("b1".to_string()), gkat.mk_var("b2".to_string()));
+    let b1b2 = gkat.mk_and(&b1, &b2);
+    let nb2 = gkat.mk_not(&b2);
+    assert!(!gkat.is_false(&b1b2));
+    assert!(!gkat.is_equiv(&b1, &nb2));
+    // equivalence is symmetric, so the cached answer serves both orders
+    let hits = gkat.stats().cache_hits;
+    assert!(!gkat.is_equiv(&nb2, &b1));
+    assert_eq!(gkat.stats().cache_hits, hits + 1);
+    for b in input.constraints {
+        let b = gkat.from_bexp(b);
+        gkat.add_constraint(&b);
+    }
+    assert!(gkat.is_false(&b1b2));
+    let b1nb2 = gkat.mk_and(&b1, &nb2);
+    assert!(gkat.is_equiv(&b1, &b1nb2));
+    let atom = gkat.pick_atom(&b1).unwrap();
+    assert!(atom.0.contains(&("b2".to_string(), false)));
+    // both kernels relativise their queries
+    let Goal::Equiv(exp1, exp2) = input.goal else {
+        unreachable!()
+    };
+    let exp1 = gkat.from_exp(exp1);
+    let exp2 = gkat.from_exp(exp2);
+    let mut solver = kernel1::Solver::new();
+    assert_eq!(solver.equiv_iter(&mut gkat, &exp1, &exp2), Ok(true));
+    let mut solver = kernel2::Solver::new();
+    let (i, m) = solver.mk_automaton(&mut gkat, &exp1);
+    let (j, n) = solver.mk_automaton(&mut gkat, &exp2);
+    assert_eq!(solver.equiv_iter(&mut gkat, i, j, &m, &n), Ok(true));
+    // an and gate over n inputs takes n + 1 clauses, and shared
+    // subformulas are encoded once
+    let mut gkat = SATGkat::new();
+    let (x, y) = (gkat.mk_var("x".to_string()), gkat.mk_var("y".to_string()));
+    let xy = gkat.mk_and(&x, &y);
+    let lit = encode(&mut gkat.cnf, &gkat.man, xy);
+    assert_eq!(gkat.cnf.clauses, 3);
+    assert_eq!(encode(&mut gkat.cnf, &gkat.man, xy), lit);
+    assert_eq!(gkat.cnf.clauses, 3);
+    // the incremental encoding agrees with BDDs on random guards, across
+    // restarts of the solver; atoms satisfy the guard they were picked from
+    let mut rng = Lcg::new(1);
+    let guards: Vec<_> = (0..200).map(|_| random_bexp(&mut rng, 7, 10)).collect();
+    let mut bdd = PureBDDGkat::new();
+    let bs: Vec<_> = guards.iter().map(|b| bdd.from_bexp(b.clone())).collect();
+    let cs: Vec<_> = guards.iter().map(|b| gkat.from_bexp(b.clone())).collect();
+    for i in 0..bs.len() {
+        assert_eq!(bdd.is_false(&bs[i]), gkat.is_false(&cs[i]));
+        let j = (i * 7 + 3) % bs.len();
+        assert_eq!(bdd.is_equiv(&bs[i], &bs[j]), gkat.is_equiv(&cs[i], &cs[j]));
+        match gkat.pick_atom(&cs[i]) {
+            Some(atom) => {
+                let a = bdd.mk_atom(&atom);
+                let ab = bdd.mk_and(&a, &bs[i]);
+                assert!(!bdd.is_false(&ab));
+            }
+            None => assert!(bdd.is_false(&bs[i])),
+        }
+    }
+    assert!(gkat.stats().sat_calls > 0);
 }
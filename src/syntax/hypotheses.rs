@@ -0,0 +1,104 @@
+use super::*;
+use crate::hash::{HashMap, HashMapExt};
+use crate::parsing;
+
+// Hoare hypotheses b;p = b;p;c, stored per action as a partition of
+// the atoms before p into guards with the test known to hold after p.
+#[derive(Debug, Clone)]
+pub struct Hypotheses<B> {
+    post: HashMap<u64, Vec<(B, B)>>,
+}
+
+impl<B: BExp> Default for Hypotheses<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: BExp> Hypotheses<B> {
+    pub fn new() -> Self {
+        Hypotheses {
+            post: HashMap::new(),
+        }
+    }
+
+    pub fn from_raw<G: Gkat<B>>(gkat: &mut G, raw: Vec<parsing::Hypothesis>) -> Self {
+        let mut hyps = Self::new();
+        for h in raw {
+            let a = match gkat.mk_act(h.act).get() {
+                Exp_::Act(a) => *a,
+                _ => unreachable!(),
+            };
+            let pre = gkat.from_bexp(h.pre);
+            let post = gkat.from_bexp(h.post);
+            hyps.add(gkat, &pre, a, &post);
+        }
+        hyps
+    }
+
+    // Record b;a = b;a;c by splitting the guards of a on b.
+    pub fn add<G: Gkat<B>>(&mut self, gkat: &mut G, b: &B, a: u64, c: &B) {
+        let one = gkat.mk_one();
+        let post = self
+            .post
+            .entry(a)
+            .or_insert_with(|| vec![(one.clone(), one)]);
+        let nb = gkat.mk_not(b);
+        let mut split = vec![];
+        for (g, d) in post.drain(..) {
+            let g1 = gkat.mk_and(&g, b);
+            if !gkat.is_false(&g1) {
+                split.push((g1, gkat.mk_and(&d, c)));
+            }
+            let g2 = gkat.mk_and(&g, &nb);
+            if !gkat.is_false(&g2) {
+                split.push((g2, d));
+            }
+        }
+        *post = split;
+    }
+
+    // Guards before a paired with the tests holding after it.
+    #[inline]
+    pub fn get(&self, a: u64) -> Option<&Vec<(B, B)>> {
+        self.post.get(&a)
+    }
+}
+
+#[test]
+fn test() {
+    use crate::{
+        kernel1, kernel2,
+        parsing::{Goal, parse},
+    };
+    let cases = [
+        // b41 holds after p8
+        "(hypothesis 1 p8 b41) (seq p8 (if b41 p1 p2)) (seq p8 p1) (equiv 1)",
+        // p2 does not change b15
+        "(hypothesis b15 p2 b15) (hypothesis (not b15) p2 (not b15))
+         (while b15 p2) (if b15 (while 1 p2) (test 1)) (equiv 1)",
+        "(hypothesis b1 p1 b2) (seq p1 (test b2)) p1 (equiv 0)",
+        "(hypothesis b1 p1 b2) (seq (test b1) p1 (test b2)) (seq (test b1) p1) (equiv 1)",
+    ];
+    for s in cases {
+        let input = parse(s.to_string()).unwrap();
+        let mut gkat = PureBDDGkat::new();
+        let hyps = Hypotheses::from_raw(&mut gkat, input.hyps);
+        let Goal::Equiv(exp1, exp2) = input.goal else {
+            unreachable!()
+        };
+        let exp1 = gkat.from_exp(exp1);
+        let exp2 = gkat.from_exp(exp2);
+        // without hypotheses the expressions differ
+        let mut solver = kernel1::Solver::new();
+        assert_eq!(solver.equiv_iter(&mut gkat, &exp1, &exp2), Ok(false));
+        let mut solver = kernel1::Solver::new();
+        solver.set_hypotheses(hyps.clone());
+        assert_eq!(solver.equiv_iter(&mut gkat, &exp1, &exp2), Ok(input.expected));
+        let mut solver = kernel2::Solver::new();
+        solver.set_hypotheses(hyps);
+        let (i, m) = solver.mk_automaton(&mut gkat, &exp1);
+        let (j, n) = solver.mk_automaton(&mut gkat, &exp2);
+        assert_eq!(solver.equiv_iter(&mut gkat, i, j, &m, &n), Ok(input.expected));
+    }
+}
@@ -0,0 +1,43 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+// Resource limits on a check; None means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub timeout: Option<Duration>,
+    // explored state pairs
+    pub max_pairs: Option<usize>,
+    // live BDD nodes
+    pub max_nodes: Option<usize>,
+    // conflicts per SAT call
+    pub max_conflicts: Option<usize>,
+}
+
+impl Limits {
+    pub fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|t| Instant::now() + t)
+    }
+}
+
+// Limit that stopped a check before it reached a verdict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Timeout,
+    Pairs,
+    Nodes,
+    Conflicts,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Limit::Timeout => "timeout",
+            Limit::Pairs => "state pair limit reached",
+            Limit::Nodes => "BDD node limit reached",
+            Limit::Conflicts => "SAT conflict budget exhausted",
+        };
+        write!(f, "{}", reason)
+    }
+}
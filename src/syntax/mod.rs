@@ -1,7 +1,42 @@
+mod actions;
+mod cover;
+mod edge;
 mod gkat;
+#[cfg(feature = "sat")]
+mod gkat_aig;
+#[cfg(feature = "cudd")]
 mod gkat_bdd;
+mod gkat_explicit;
+#[cfg(feature = "sat")]
+mod gkat_hybrid;
+mod gkat_pure_bdd;
+#[cfg(feature = "sat")]
 mod gkat_sat;
+mod hypotheses;
+mod limits;
+mod order;
+mod stats;
+#[cfg(test)]
+pub mod testing;
+#[cfg(feature = "sat")]
+mod tseitin;
+mod witness;
 
+pub use actions::*;
+pub use cover::*;
 pub use gkat::*;
+#[cfg(feature = "sat")]
+pub use gkat_aig::*;
+#[cfg(feature = "cudd")]
 pub use gkat_bdd::*;
+pub use gkat_explicit::*;
+#[cfg(feature = "sat")]
+pub use gkat_hybrid::*;
+pub use gkat_pure_bdd::*;
+#[cfg(feature = "sat")]
 pub use gkat_sat::*;
+pub use hypotheses::*;
+pub use limits::*;
+pub use order::*;
+pub use stats::*;
+pub use witness::*;
@@ -0,0 +1,200 @@
+use crate::hash::{HashMap, HashMapExt};
+use crate::parsing::{BExp, Exp, Goal, Input};
+use clap::ValueEnum;
+
+// CUDD dynamic reordering method.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Reorder {
+    #[default]
+    None,
+    Sift,
+    SiftConverge,
+    SymmSift,
+    GroupSift,
+    Window,
+    Annealing,
+    Genetic,
+    Exact,
+}
+
+// Order in which test variables are created.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum VarOrder {
+    // order of first occurrence while building the expressions
+    #[default]
+    FirstSeen,
+    // computed from the guards by static_order
+    Static,
+    Explicit(Vec<String>),
+}
+
+impl VarOrder {
+    // Variables to create up front, before any expression is built.
+    pub fn vars(&self, guards: &[Vec<String>]) -> Vec<String> {
+        match self {
+            VarOrder::FirstSeen => vec![],
+            VarOrder::Static => static_order(guards),
+            VarOrder::Explicit(vars) => vars.clone(),
+        }
+    }
+}
+
+// Variables of every guard of exp, one list per guard.
+pub fn exp_guards(exp: &Exp, guards: &mut Vec<Vec<String>>) {
+    let guard = |b: &BExp, guards: &mut Vec<Vec<String>>| {
+        let mut vars = vec![];
+        bexp_vars(b, &mut vars);
+        guards.push(vars);
+    };
+    match exp {
+        Exp::Act(_) => {}
+        Exp::Seq(p1, p2) => {
+            exp_guards(p1, guards);
+            exp_guards(p2, guards);
+        }
+        Exp::Ifte(b, p1, p2) => {
+            guard(b, guards);
+            exp_guards(p1, guards);
+            exp_guards(p2, guards);
+        }
+        Exp::Test(b) => guard(b, guards),
+        Exp::While(b, p) => {
+            guard(b, guards);
+            exp_guards(p, guards);
+        }
+    }
+}
+
+// Guards of an input file, including constraints and hypotheses.
+pub fn input_guards(input: &Input) -> Vec<Vec<String>> {
+    let mut guards = vec![];
+    let mut tests: Vec<&BExp> = input.constraints.iter().collect();
+    for h in &input.hyps {
+        tests.push(&h.pre);
+        tests.push(&h.post);
+    }
+    match &input.goal {
+        Goal::Equiv(p1, p2) | Goal::Includes(p1, p2) => {
+            exp_guards(p1, &mut guards);
+            exp_guards(p2, &mut guards);
+        }
+        Goal::Triple(pre, p, post) => {
+            tests.push(pre);
+            tests.push(post);
+            exp_guards(p, &mut guards);
+        }
+    }
+    for b in tests {
+        let mut vars = vec![];
+        bexp_vars(b, &mut vars);
+        guards.push(vars);
+    }
+    guards
+}
+
+fn bexp_vars(b: &BExp, vars: &mut Vec<String>) {
+    match b {
+        BExp::Zero | BExp::One => {}
+        BExp::PBool(s) => {
+            if !vars.contains(s) {
+                vars.push(s.clone());
+            }
+        }
+        BExp::And(b1, b2) | BExp::Or(b1, b2) => {
+            bexp_vars(b1, vars);
+            bexp_vars(b2, vars);
+        }
+        BExp::Not(b) => bexp_vars(b, vars),
+    }
+}
+
+// Place variables that share guards next to each other, using the FORCE
+// heuristic: move every variable to the mean center of its guards until
+// the order settles, keeping the order where the guards span the least.
+pub fn static_order(guards: &[Vec<String>]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let mut edges: Vec<Vec<usize>> = vec![];
+    for g in guards {
+        let mut edge = vec![];
+        for x in g {
+            let id = *ids.entry(x).or_insert_with(|| {
+                names.push(x.clone());
+                names.len() - 1
+            });
+            edge.push(id);
+        }
+        if edge.len() > 1 {
+            edges.push(edge);
+        }
+    }
+    let n = names.len();
+    let mut pos: Vec<usize> = (0..n).collect();
+    let mut best = (span(&edges, &pos), pos.clone());
+    for _ in 0..20 {
+        let mut sum = vec![0.0; n];
+        let mut count = vec![0usize; n];
+        for e in &edges {
+            let center = e.iter().map(|x| pos[*x] as f64).sum::<f64>() / e.len() as f64;
+            for x in e {
+                sum[*x] += center;
+                count[*x] += 1;
+            }
+        }
+        let target: Vec<f64> = (0..n)
+            .map(|x| match count[x] {
+                0 => pos[x] as f64,
+                c => sum[x] / c as f64,
+            })
+            .collect();
+        let mut by_target: Vec<usize> = (0..n).collect();
+        by_target.sort_by(|x, y| {
+            target[*x]
+                .total_cmp(&target[*y])
+                .then(pos[*x].cmp(&pos[*y]))
+        });
+        let mut next = vec![0; n];
+        for (i, x) in by_target.into_iter().enumerate() {
+            next[x] = i;
+        }
+        if next == pos {
+            break;
+        }
+        pos = next;
+        let s = span(&edges, &pos);
+        if s < best.0 {
+            best = (s, pos.clone());
+        }
+    }
+    let mut order = vec![String::new(); n];
+    for (x, name) in names.into_iter().enumerate() {
+        order[best.1[x]] = name;
+    }
+    order
+}
+
+// Sum over the guards of the distance between their outermost variables.
+fn span(edges: &[Vec<usize>], pos: &[usize]) -> usize {
+    let width = |e: &Vec<usize>| {
+        let ps = e.iter().map(|x| pos[*x]);
+        ps.clone().max().unwrap() - ps.min().unwrap()
+    };
+    edges.iter().map(width).sum()
+}
+
+#[test]
+fn test() {
+    use crate::parsing::parse_exp;
+    let exp = parse_exp(
+        "(seq (if (or a b) p1 p2) (if (or c d) p1 p2) \
+         (while (and a d) p3) (if (and a (not d)) p1 p4))",
+    )
+    .unwrap();
+    let mut guards = vec![];
+    exp_guards(&exp, &mut guards);
+    // a and d share most guards, so they end up next to each other
+    assert_eq!(VarOrder::Static.vars(&guards), ["b", "a", "d", "c"]);
+    assert_eq!(VarOrder::FirstSeen.vars(&guards), Vec::<String>::new());
+    let explicit = VarOrder::Explicit(vec!["d".to_string()]);
+    assert_eq!(explicit.vars(&guards), ["d"]);
+}
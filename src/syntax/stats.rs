@@ -0,0 +1,105 @@
+use std::fmt;
+
+// Counters of a kernel1 or kernel2 solver.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SolverStats {
+    // state pairs popped by the equivalence and inclusion searches
+    pub pairs: usize,
+    pub unions: usize,
+    // kernel1 caches
+    pub eps_hits: usize,
+    pub eps_misses: usize,
+    pub drv_hits: usize,
+    pub drv_misses: usize,
+    pub is_dead_calls: usize,
+    pub dead_explored: usize,
+    // kernel2 automata built
+    pub states: usize,
+    pub transitions: usize,
+}
+
+// Counters of a Gkat backend; fields a backend lacks stay zero.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GkatStats {
+    pub is_false_calls: usize,
+    pub is_equiv_calls: usize,
+    // SATGkat
+    pub sat_calls: usize,
+    pub cache_hits: usize,
+    // AIGGkat: nodes merged by SAT sweeping
+    pub merges: usize,
+    // HybridGkat: guards and queries moved from BDDs to SAT
+    pub switches: usize,
+    // BDDGkat, PureBDDGkat and AIGGkat
+    pub nodes: usize,
+    pub peak_nodes: usize,
+    pub reorderings: usize,
+    // milliseconds spent reordering
+    pub reorder_time: usize,
+}
+
+impl GkatStats {
+    // Counters accumulated since before was taken; nodes and peak_nodes
+    // describe the backend as a whole and are kept as they are.
+    pub fn since(&self, before: &GkatStats) -> GkatStats {
+        GkatStats {
+            is_false_calls: self.is_false_calls - before.is_false_calls,
+            is_equiv_calls: self.is_equiv_calls - before.is_equiv_calls,
+            sat_calls: self.sat_calls - before.sat_calls,
+            cache_hits: self.cache_hits - before.cache_hits,
+            merges: self.merges - before.merges,
+            switches: self.switches - before.switches,
+            nodes: self.nodes,
+            peak_nodes: self.peak_nodes,
+            reorderings: self.reorderings - before.reorderings,
+            reorder_time: self.reorder_time - before.reorder_time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub solver: SolverStats,
+    pub gkat: GkatStats,
+}
+
+impl Stats {
+    // Counters by name, in a fixed order.
+    pub fn counters(&self) -> Vec<(&'static str, usize)> {
+        let (s, g) = (&self.solver, &self.gkat);
+        vec![
+            ("pairs", s.pairs),
+            ("unions", s.unions),
+            ("eps_hits", s.eps_hits),
+            ("eps_misses", s.eps_misses),
+            ("drv_hits", s.drv_hits),
+            ("drv_misses", s.drv_misses),
+            ("is_dead_calls", s.is_dead_calls),
+            ("dead_explored", s.dead_explored),
+            ("states", s.states),
+            ("transitions", s.transitions),
+            ("is_false_calls", g.is_false_calls),
+            ("is_equiv_calls", g.is_equiv_calls),
+            ("sat_calls", g.sat_calls),
+            ("cache_hits", g.cache_hits),
+            ("merges", g.merges),
+            ("switches", g.switches),
+            ("nodes", g.nodes),
+            ("peak_nodes", g.peak_nodes),
+            ("reorderings", g.reorderings),
+            ("reorder_ms", g.reorder_time),
+        ]
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, n)) in self.counters().iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:<15}= {}", name, n)?;
+        }
+        Ok(())
+    }
+}
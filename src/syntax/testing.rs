@@ -0,0 +1,75 @@
+use super::*;
+use crate::{
+    kernel1, kernel2,
+    parsing::{self, parse_exp},
+};
+
+// Guard of a test in s-expression syntax.
+pub fn parse_bexp(s: &str) -> parsing::BExp {
+    match parse_exp(&format!("(test {})", s)).unwrap() {
+        parsing::Exp::Test(b) => b,
+        _ => unreachable!(),
+    }
+}
+
+// Seeded linear congruential generator, so random tests are repeatable.
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Lcg(seed)
+    }
+
+    // Number below n.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n
+    }
+}
+
+// Random guard up to the given depth over the constants and x0..x{vars-1}.
+pub fn random_bexp(rng: &mut Lcg, depth: u32, vars: u64) -> parsing::BExp {
+    use parsing::BExp::*;
+    if depth == 0 || rng.below(4) == 0 {
+        return match rng.below(vars + 2) {
+            0 => One,
+            1 => Zero,
+            i => PBool(format!("x{}", i - 2)),
+        };
+    }
+    let op = rng.below(3);
+    let mut sub = || Box::new(random_bexp(rng, depth - 1, vars));
+    match op {
+        0 => Not(sub()),
+        1 => And(sub(), sub()),
+        _ => Or(sub(), sub()),
+    }
+}
+
+// Both kernels prove a loop with a branch equal to its mirror image.
+pub fn run_kernels<B: BExp, G: Gkat<B>>(gkat: &mut G) {
+    let exp1 = gkat.from_exp(parse_exp("(while c (seq p1 (if b p2 p3)))").unwrap());
+    let exp2 = gkat.from_exp(parse_exp("(while c (seq p1 (if (not b) p3 p2)))").unwrap());
+    assert_eq!(kernel1::Solver::new().equiv_iter(gkat, &exp1, &exp2), Ok(true));
+    let mut solver = kernel2::Solver::new();
+    let (i, m) = solver.mk_automaton(gkat, &exp1);
+    let (j, n) = solver.mk_automaton(gkat, &exp2);
+    assert_eq!(solver.equiv_iter(gkat, i, j, &m, &n), Ok(true));
+}
+
+#[test]
+fn test() {
+    run_kernels(&mut PureBDDGkat::new());
+    run_kernels(&mut ExplicitGkat::new(2));
+    #[cfg(feature = "sat")]
+    {
+        run_kernels(&mut SATGkat::new());
+        run_kernels(&mut AIGGkat::new());
+        run_kernels(&mut HybridGkat::new());
+    }
+    #[cfg(feature = "cudd")]
+    run_kernels(&mut BDDGkat::new());
+}
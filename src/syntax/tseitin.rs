@@ -0,0 +1,110 @@
+use crate::hash::HashMap;
+use logicng::solver::minisat::sat::{MiniSat2Solver, MsLit, Tristate, mk_lit, not, var};
+use std::hash::Hash;
+
+// Tseitin clauses kept on a solver before it starts over. Every query
+// decides all inputs and so propagates through every gate encoded so far;
+// on the e1000 and e2000 benchmarks keeping up to ~1000 clauses is as fast
+// as re-encoding each query, 30k clauses is 5x slower and 300k 50x.
+const MAX_CLAUSES: usize = 1000;
+
+// Incremental solver with the Tseitin encoding of shared subformulas, each
+// encoded once under its key. Only inputs are decision variables; the
+// clauses that define a gate propagate its value from the inputs.
+pub struct Tseitin<K> {
+    solver: MiniSat2Solver,
+    lits: HashMap<K, MsLit>,
+    pub clauses: usize,
+}
+
+impl<K: Hash + Eq> Tseitin<K> {
+    pub fn new() -> Self {
+        Self {
+            solver: MiniSat2Solver::new(),
+            lits: HashMap::default(),
+            clauses: 0,
+        }
+    }
+
+    // Start over when the encoding has grown past MAX_CLAUSES.
+    pub fn restart(&mut self) {
+        if self.clauses > MAX_CLAUSES {
+            *self = Self::new();
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, k: &K) -> Option<MsLit> {
+        self.lits.get(k).copied()
+    }
+
+    pub fn insert(&mut self, k: K, x: MsLit) {
+        self.lits.insert(k, x);
+    }
+
+    pub fn remove(&mut self, k: &K) {
+        self.lits.remove(k);
+    }
+
+    pub fn input(&mut self) -> MsLit {
+        mk_lit(self.solver.new_var(true, true), false)
+    }
+
+    pub fn gate(&mut self) -> MsLit {
+        mk_lit(self.solver.new_var(true, false), false)
+    }
+
+    // Gate that always holds.
+    pub fn one(&mut self) -> MsLit {
+        let x = self.gate();
+        self.add_clause(vec![x]);
+        x
+    }
+
+    // Gate x with x <-> (op1 and ... and opn).
+    pub fn and(&mut self, ops: &[MsLit]) -> MsLit {
+        let x = self.gate();
+        let mut clause = vec![x];
+        for op in ops {
+            self.add_clause(vec![not(x), *op]);
+            clause.push(not(*op));
+        }
+        self.add_clause(clause);
+        x
+    }
+
+    // Gate x with x <-> (x1 <-> x2).
+    pub fn equiv(&mut self, x1: MsLit, x2: MsLit) -> MsLit {
+        let x = self.gate();
+        self.add_clause(vec![not(x), not(x1), x2]);
+        self.add_clause(vec![not(x), x1, not(x2)]);
+        self.add_clause(vec![x, x1, x2]);
+        self.add_clause(vec![x, not(x1), not(x2)]);
+        x
+    }
+
+    pub fn add_clause(&mut self, clause: Vec<MsLit>) {
+        self.clauses += 1;
+        self.solver.add_clause(clause, &None);
+    }
+
+    pub fn solve(&mut self, lits: Vec<MsLit>) -> Tristate {
+        self.solver.solve_with_assumptions(lits)
+    }
+
+    // Solve for lits within n conflicts. The budget needs a single restart,
+    // which cannot take assumptions, so the lits become unit clauses and
+    // the solver is good for this query only.
+    pub fn search(mut self, lits: Vec<MsLit>, n: usize) -> Tristate {
+        for x in lits {
+            self.add_clause(vec![x]);
+        }
+        self.solver.search(n)
+    }
+
+    // Value of the input under k in the model of the last call, false when
+    // the last query did not involve it.
+    pub fn value(&self, k: &K) -> bool {
+        self.get(k).is_some_and(|x| self.solver.model[var(x).0])
+    }
+}
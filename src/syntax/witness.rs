@@ -0,0 +1,125 @@
+use super::*;
+use std::fmt;
+
+// Atom: a full truth assignment to every test variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Atom(pub Vec<(String, bool)>);
+
+// Guarded string alternating atoms and actions: a0 p1 a1 ... pn an.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardedString {
+    pub atoms: Vec<Atom>,
+    pub acts: Vec<String>,
+}
+
+// Guarded string accepted by exactly one of two expressions.
+#[derive(Debug, Clone)]
+pub struct Witness {
+    pub string: GuardedString,
+    // whether the first expression is the accepting side
+    pub left_accepts: bool,
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<")?;
+        for (i, (s, v)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            if *v {
+                write!(f, "{}", s)?;
+            } else {
+                write!(f, "!{}", s)?;
+            }
+        }
+        write!(f, ">")
+    }
+}
+
+impl fmt::Display for GuardedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (atom, act) in self.atoms.iter().zip(self.acts.iter()) {
+            write!(f, "{} {} ", atom, act)?;
+        }
+        match self.atoms.last() {
+            Some(atom) => write!(f, "{}", atom),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Witness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = if self.left_accepts { "left" } else { "right" };
+        write!(f, "{} (accepted by {})", self.string, side)
+    }
+}
+
+// Symbolic description of a distinguishing guarded string. S is the kind
+// of state the kernel explores: an expression or an automaton state.
+pub struct Mismatch<B, S> {
+    // guards and actions leading to the failing pair
+    pub path: Vec<(B, u64)>,
+    // atoms on which the failing pair disagrees
+    pub guard: B,
+    // action and live successor of the accepting side
+    pub suffix: Option<(u64, S)>,
+    // whether the first side is the accepting one
+    pub left_accepts: bool,
+}
+
+impl<B: BExp, S> Mismatch<B, S> {
+    // Follow the parent pointers of trace from parent back to the start.
+    pub fn new(
+        trace: &[(Option<usize>, B, u64)],
+        parent: Option<usize>,
+        guard: B,
+        suffix: Option<(u64, S)>,
+        left_accepts: bool,
+    ) -> Self {
+        let mut path = vec![];
+        let mut parent = parent;
+        while let Some(i) = parent {
+            let (p, b, a) = &trace[i];
+            path.push((b.clone(), *a));
+            parent = *p;
+        }
+        path.reverse();
+        Self {
+            path,
+            guard,
+            suffix,
+            left_accepts,
+        }
+    }
+}
+
+// Pick an atom for every step of a symbolic path ending in guard last.
+pub fn mk_guarded_string<B: BExp, G: Gkat<B>>(
+    gkat: &mut G,
+    steps: &[(B, u64)],
+    last: &B,
+) -> GuardedString {
+    let mut atoms = Vec::with_capacity(steps.len() + 1);
+    let mut acts = Vec::with_capacity(steps.len());
+    for (b, a) in steps {
+        atoms.push(gkat.pick_atom(b).unwrap());
+        acts.push(gkat.act_name(*a));
+    }
+    atoms.push(gkat.pick_atom(last).unwrap());
+    GuardedString {
+        atoms,
+        acts,
+    }
+}
+
+// Pairs of inequivalent expressions the witness tests of both kernels use.
+#[cfg(test)]
+pub const WITNESS_CASES: [(&str, &str); 5] = [
+    ("(seq p1 p2)", "(seq p1 p3)"),
+    ("(if b1 p1 p2)", "(if b2 p1 p2)"),
+    ("(while b1 p1)", "(while b1 (seq p1 p1))"),
+    ("(seq (test b1) p1)", "p1"),
+    ("(if b1 p1 (test 0))", "(seq p1 p2)"),
+];
@@ -171,21 +171,15 @@ impl Parser {
     fn parse_sequence(&mut self) -> Result<Exp, String> {
         let left = self.parse_statement()?;
 
-        // Check if the current token is a semicolon (not the peek token)
+        // Every statement moves past its last token, so only the current
+        // token can be our semicolon; the peek token may belong to an
+        // enclosing sequence after a closing brace
         if self.current_token == Token::Semicolon {
             self.next_token(); // move to the token after semicolon
             let right = self.parse_sequence()?;
             return Ok(Exp::Seq(Box::new(left), Box::new(right)));
         }
 
-        // Check if the peek token is a semicolon
-        if self.peek_token == Token::Semicolon {
-            self.next_token(); // consume semicolon
-            self.next_token(); // move to the token after semicolon
-            let right = self.parse_sequence()?;
-            return Ok(Exp::Seq(Box::new(left), Box::new(right)));
-        }
-
         Ok(left)
     }
 
@@ -451,6 +445,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_sequence_after_block() {
+        // the semicolon after a closing brace continues the outer sequence
+        let result = parse_expression("while b1 { while b2 { P1 }; P2 }; P3");
+        match result.unwrap() {
+            Exp::Seq(left, right) => {
+                let Exp::While(_, body) = *left else {
+                    panic!("expected a loop first")
+                };
+                assert!(matches!(*body, Exp::Seq(_, _)));
+                assert!(matches!(*right, Exp::Act(_)));
+            }
+            exp => panic!("expected a sequence, got {:?}", exp),
+        }
+    }
+
     #[test]
     fn test_parse_if_then_else() {
         let result = parse_expression("if b1 { P1 } else { P2 }");